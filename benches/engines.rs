@@ -0,0 +1,78 @@
+//! Compares `Interpreter` with `FastInterpreter`. Run with `cargo bench`.
+
+extern crate aocrs;
+
+use aocrs::intcode::{parse_bytecode_string, FastInterpreter, Interpreter, Status};
+
+use std::fs;
+use std::time::{Duration, Instant};
+
+// the day 2 noun/verb search, every combination of both
+fn day_2_search(src: &[isize], run: &dyn Fn(&[isize]) -> isize) -> usize {
+    let mut found = 0;
+    for noun in 0..100 {
+        for verb in 0..100 {
+            let mut memory = src.to_vec();
+            memory[1] = noun;
+            memory[2] = verb;
+            if run(&memory) == 19690720 {
+                found += 1;
+            }
+        }
+    }
+    found
+}
+
+// counts down from `n`, printing nothing, to measure a tight loop
+fn countdown(n: isize) -> Vec<isize> {
+    vec![1101, 0, n, 13, 1001, 13, -1, 13, 1005, 13, 4, 99, 0, 0]
+}
+
+fn bench(name: &str, iterations: u32, f: &dyn Fn()) -> Duration {
+    f();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let elapsed = start.elapsed() / iterations;
+    println!("{:<32} {:>12?}", name, elapsed);
+    elapsed
+}
+
+fn interpreter_result(memory: &[isize]) -> isize {
+    let mut program = Interpreter::from_bytecode(memory);
+    match program.run() {
+        Ok(Status::Halted) => program.read(0),
+        _ => -1,
+    }
+}
+
+fn fast_result(memory: &[isize]) -> isize {
+    let mut program = FastInterpreter::from_bytecode(memory);
+    match program.run() {
+        Ok(Status::Halted) => program.read(0),
+        _ => -1,
+    }
+}
+
+fn main() {
+    let src = fs::read_to_string("data/day_2_intcode.txt").unwrap();
+    let day_2 = parse_bytecode_string(&src).unwrap();
+
+    let slow = bench("day 2 search, Interpreter", 5, &|| {
+        day_2_search(&day_2, &interpreter_result);
+    });
+    let fast = bench("day 2 search, FastInterpreter", 5, &|| {
+        day_2_search(&day_2, &fast_result);
+    });
+    println!("speedup {:.1}x\n", slow.as_secs_f64() / fast.as_secs_f64());
+
+    let program = countdown(1_000_000);
+    let slow = bench("countdown, Interpreter", 5, &|| {
+        interpreter_result(&program);
+    });
+    let fast = bench("countdown, FastInterpreter", 5, &|| {
+        fast_result(&program);
+    });
+    println!("speedup {:.1}x", slow.as_secs_f64() / fast.as_secs_f64());
+}
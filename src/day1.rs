@@ -1,4 +1,4 @@
-pub fn calculate_total_fuel(masses: &Vec<u32>) -> u32 {
+pub fn calculate_total_fuel(masses: &[u32]) -> u32 {
     masses.iter().fold(0, |acc, &x| acc + calculate_fuel(x))
 }
 
@@ -1,6 +1,10 @@
-use crate::intcode::Interpreter;
+use std::error::Error;
 
-pub fn run(src: &Vec<isize>) {
+use crate::intcode::{solve, FastInterpreter, Status, SymbolicMachine};
+
+const TARGET: isize = 19690720;
+
+pub fn run(src: &[isize]) {
     // Part 1
     // the challenge says we have to do this
     // intcode_src[1] = 12;
@@ -10,18 +14,65 @@ pub fn run(src: &Vec<isize>) {
 
     // println!("value at position 0: {}", response);
 
+    match solve_symbolically(src) {
+        Ok((noun, verb)) => {
+            println!(
+                "Found answer {} with noun {} and verb {}",
+                TARGET, noun, verb
+            );
+            return;
+        }
+        Err(err) => println!("no symbolic solution ({}), searching instead", err),
+    }
+
     'outer: for i in 0..100 {
         for j in 0..100 {
-            let mut test_memory = src.clone();
+            let mut test_memory = src.to_vec();
             test_memory[1] = i;
             test_memory[2] = j;
 
-            let mut program = Interpreter::from_bytecode(&test_memory);
-            program.execute();
-            if program.read(0) == 19690720 {
-                println!("Found answer {} with noun {} and verb {}", 19690720, i, j);
+            let mut program = FastInterpreter::from_bytecode(&test_memory);
+            if program.run() == Ok(Status::Halted) && program.read(0) == TARGET {
+                println!("Found answer {} with noun {} and verb {}", TARGET, i, j);
                 break 'outer;
             }
         }
     }
 }
+
+// runs the program once with noun and verb left as symbols, then solves
+// the resulting expression for [0]
+fn solve_symbolically(src: &[isize]) -> Result<(isize, isize), Box<dyn Error>> {
+    let mut machine = SymbolicMachine::new(src);
+    machine.symbolize(1, "noun");
+    machine.symbolize(2, "verb");
+    machine.run()?;
+
+    let values = solve(
+        &machine.read(0),
+        TARGET,
+        &[("noun", 0..=99), ("verb", 0..=99)],
+    )?;
+    Ok((values[0], values[1]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::parse_bytecode_string;
+    use std::fs;
+
+    #[test]
+    fn test_symbolic_matches_search() {
+        let src =
+            parse_bytecode_string(&fs::read_to_string("data/day_2_intcode.txt").unwrap()).unwrap();
+        let (noun, verb) = solve_symbolically(&src).unwrap();
+
+        let mut memory = src.clone();
+        memory[1] = noun;
+        memory[2] = verb;
+        let mut program = FastInterpreter::from_bytecode(&memory);
+        assert_eq!(program.run(), Ok(Status::Halted));
+        assert_eq!(program.read(0), TARGET);
+    }
+}
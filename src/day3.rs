@@ -7,38 +7,32 @@ pub fn run(wire_a_input: &str, wire_b_input: &str) -> Option<u32> {
     let wire_a = gen_lines(&direction::parse_input(wire_a_input));
     let wire_b = gen_lines(&direction::parse_input(wire_b_input));
 
-    match find_intersection_point_pt_2(&wire_a, &wire_b) {
-        Some((_, dist)) => Some(dist),
-        None => None,
-    }
+    find_intersection_point_pt_2(&wire_a, &wire_b).map(|(_, dist)| dist)
 }
 
 fn find_intersection_point_pt_2(
-    wire_a: &Vec<LineSegment>,
-    wire_b: &Vec<LineSegment>,
+    wire_a: &[LineSegment],
+    wire_b: &[LineSegment],
 ) -> Option<(Vector, u32)> {
     let mut min: Option<(Vector, u32)> = None;
 
     wire_a.iter().fold(0, |distance_a, segment_a| {
         wire_b.iter().fold(0, |distance_b, segment_b| {
-            match intersects(segment_a, segment_b) {
-                Some(point) => {
-                    let dist = distance_a
-                        + manhattan_distance(&segment_a.0, &point)
-                        + distance_b
-                        + manhattan_distance(&segment_b.0, &point);
-
-                    match min {
-                        Some((_, min_so_far)) => {
-                            if (dist < min_so_far) {
-                                min = Some((point, dist));
-                            }
+            if let Some(point) = intersects(segment_a, segment_b) {
+                let dist = distance_a
+                    + manhattan_distance(&segment_a.0, &point)
+                    + distance_b
+                    + manhattan_distance(&segment_b.0, &point);
+
+                match min {
+                    Some((_, min_so_far)) => {
+                        if dist < min_so_far {
+                            min = Some((point, dist));
                         }
-
-                        None => min = Some((point, dist)),
                     }
+
+                    None => min = Some((point, dist)),
                 }
-                None => (),
             }
 
             distance_b + manhattan_distance(&segment_b.0, &segment_b.1)
@@ -50,31 +44,6 @@ fn find_intersection_point_pt_2(
     min
 }
 
-fn find_intersection_point_pt_1(
-    a: &Vec<LineSegment>,
-    b: &Vec<LineSegment>,
-) -> Option<(Vector, u32)> {
-    let mut min_point: Option<(Vector, u32)> = None;
-
-    a.iter().for_each(|segment_a| {
-        b.iter()
-            .for_each(|segment_b| match intersects(segment_a, segment_b) {
-                Some(point) => match min_point {
-                    Some((_, min_dist)) => {
-                        let dist = manhattan_distance(&point, &ORIGIN);
-                        if dist < min_dist {
-                            min_point = Some((point, dist));
-                        }
-                    }
-                    None => min_point = Some((point, manhattan_distance(&point, &ORIGIN))),
-                },
-                None => (),
-            });
-    });
-
-    min_point
-}
-
 /**
  * http://www.cs.swan.ac.uk/~cssimon/line_intersection.html
  */
@@ -87,7 +56,7 @@ fn intersects(a: &LineSegment, b: &LineSegment) -> Option<Vector> {
     let tb = ((y1 - y2) * (x1 - x3) + (x2 - x1) * (y1 - y3)) as f32
         / ((x4 - x3) * (y1 - y2) - (x1 - x2) * (y4 - y3)) as f32;
 
-    if ta >= 0.0 && ta <= 1.0 && tb >= 0.0 && tb <= 1.0 {
+    if (0.0..=1.0).contains(&ta) && (0.0..=1.0).contains(&tb) {
         Some((
             x1 + (ta * (x2 - x1) as f32) as i32,
             y1 + (ta * (y2 - y1) as f32) as i32,
@@ -97,11 +66,11 @@ fn intersects(a: &LineSegment, b: &LineSegment) -> Option<Vector> {
     }
 }
 
-fn gen_lines(dirs: &Vec<Vector>) -> Vec<LineSegment> {
+fn gen_lines(dirs: &[Vector]) -> Vec<LineSegment> {
     dirs.iter().fold(Vec::new(), |list, delta| {
         let new_line = match list.last() {
-            Some((_, prev)) => (*prev, next_point(&prev, &delta)),
-            None => (ORIGIN, next_point(&ORIGIN, &delta)),
+            Some((_, prev)) => (*prev, next_point(prev, delta)),
+            None => (ORIGIN, next_point(&ORIGIN, delta)),
         };
 
         [list, vec![new_line]].concat()
@@ -109,7 +78,7 @@ fn gen_lines(dirs: &Vec<Vector>) -> Vec<LineSegment> {
 }
 
 fn next_point(start: &Vector, delta: &Vector) -> Vector {
-    return (start.0 + delta.0, start.1 + delta.1);
+    (start.0 + delta.0, start.1 + delta.1)
 }
 
 fn manhattan_distance(a: &Vector, b: &Vector) -> u32 {
@@ -133,7 +102,7 @@ mod direction {
                     "R" => right(delta),
                     "D" => down(delta),
                     "L" => left(delta),
-                    _ => panic!(format!("Unrecognized input {} {}", dir, delta)),
+                    _ => panic!("Unrecognized input {} {}", dir, delta),
                 }
             })
             .collect()
@@ -148,17 +117,16 @@ mod direction {
     }
 
     pub fn down(dy: u32) -> Vector {
-        (0, -1 * dy as i32)
+        (0, -(dy as i32))
     }
 
     pub fn left(dx: u32) -> Vector {
-        (-1 * dx as i32, 0)
+        (-(dx as i32), 0)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::direction::*;
     use super::*;
 
     #[test]
@@ -173,13 +141,4 @@ mod tests {
             assert_eq!(intersects(&line_1, &line_2), expected);
         });
     }
-
-    // #[test]
-    // fn test_pt_1() {
-    //     let wire_a = vec![right(8), up(5), left(5), down(3)];
-    //     let wire_b = vec![up(7), right(6), down(4), left(4)];
-
-    //     let point = find_intersection_point_pt_1(&wire_a, &wire_b);
-    //     assert_eq!(point, Some((3, 3)));
-    // }
 }
@@ -0,0 +1,713 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::io::prelude::*;
+use std::io::{self, BufRead};
+
+use super::ascii::{AsciiComputer, AsciiOutput};
+use super::error::IntcodeError;
+use super::interpreter::Interpreter;
+use super::snapshot::Snapshot;
+
+/// Instructions a trial run gets to show that picking an item up doesn't
+/// hang or end the game.
+const TRIAL_BUDGET: u64 = 1_000_000;
+
+/// A text adventure the explorer can play: something that takes a command
+/// and answers with lines of text, and that can be forked to try a command
+/// out without committing to it.
+pub trait Game: Sized {
+    fn send(&mut self, command: &str) -> Result<Vec<String>, IntcodeError>;
+    fn is_over(&self) -> bool;
+    /// An independent copy of the game in its current state, for trial runs.
+    fn fork(&self) -> Self;
+}
+
+impl Game for AsciiComputer {
+    fn send(&mut self, command: &str) -> Result<Vec<String>, IntcodeError> {
+        self.send_line(command);
+        Ok(self.run()?.into_iter().map(to_text).collect())
+    }
+
+    fn is_over(&self) -> bool {
+        self.is_halted()
+    }
+
+    fn fork(&self) -> AsciiComputer {
+        let mut interpreter = Interpreter::from_snapshot(&self.interpreter().snapshot());
+        interpreter.set_instruction_budget(Some(TRIAL_BUDGET));
+        AsciiComputer::new(interpreter)
+    }
+}
+
+fn to_text(output: AsciiOutput) -> String {
+    match output {
+        AsciiOutput::Line(line) => line,
+        AsciiOutput::Value(val) => val.to_string(),
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Room {
+    pub name: String,
+    pub doors: Vec<String>,
+    pub items: Vec<String>,
+}
+
+/// The last room described in a chunk of game output, e.g.
+///
+/// ```text
+/// == Hull Breach ==
+/// You got in through a hole in the floor here.
+///
+/// Doors here lead:
+/// - north
+///
+/// Items here:
+/// - mug
+/// ```
+pub fn parse_room(lines: &[String]) -> Option<Room> {
+    let mut room: Option<Room> = None;
+    let mut list: Option<&str> = None;
+
+    for line in lines {
+        let line = line.trim();
+        if let Some(name) = room_name(line) {
+            room = Some(Room {
+                name: String::from(name),
+                ..Room::default()
+            });
+            list = None;
+        } else if line == "Doors here lead:" || line == "Items here:" {
+            list = Some(line);
+        } else if let (Some(entry), Some(room), Some(list)) =
+            (line.strip_prefix("- "), room.as_mut(), list)
+        {
+            match list {
+                "Doors here lead:" => room.doors.push(String::from(entry)),
+                _ => room.items.push(String::from(entry)),
+            }
+        } else {
+            list = None;
+        }
+    }
+
+    room
+}
+
+fn room_name(line: &str) -> Option<&str> {
+    line.trim().strip_prefix("== ")?.strip_suffix(" ==")
+}
+
+/// Describes the room the droid is in once more, for when the description
+/// is gone, e.g. in a game resumed from a snapshot. A fork of the game walks
+/// out through the first door that works and back in, so the game itself
+/// stays put.
+pub fn look_around<G: Game>(game: &G) -> Option<Vec<String>> {
+    for door in &["north", "south", "east", "west"] {
+        let mut trial = game.fork();
+        let lines = match trial.send(door) {
+            Ok(lines) if !trial.is_over() && parse_room(&lines).is_some() => lines,
+            _ => continue,
+        };
+
+        // thrown back by the pressure plate, so the last room is this one
+        if lines.iter().filter_map(|line| room_name(line)).count() > 1 {
+            return Some(lines);
+        }
+
+        match trial.send(opposite(door)) {
+            Ok(lines) if !trial.is_over() && parse_room(&lines).is_some() => return Some(lines),
+            _ => (),
+        }
+    }
+
+    None
+}
+
+fn opposite(door: &str) -> &str {
+    match door {
+        "north" => "south",
+        "south" => "north",
+        "east" => "west",
+        "west" => "east",
+        other => other,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ExploreError {
+    Intcode(IntcodeError),
+    /// The explorer was started somewhere that doesn't describe a room.
+    NotARoom,
+    /// The game ended while exploring, with what it printed last.
+    GameOver(Vec<String>),
+    /// No room bounced the droid back, so there is no pressure plate.
+    NoCheckpoint,
+    /// Every combination of the collected items was tried.
+    NoCombination,
+}
+
+impl From<IntcodeError> for ExploreError {
+    fn from(err: IntcodeError) -> ExploreError {
+        ExploreError::Intcode(err)
+    }
+}
+
+impl fmt::Display for ExploreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExploreError::Intcode(err) => write!(f, "{}", err),
+            ExploreError::NotARoom => write!(f, "not standing in a room"),
+            ExploreError::GameOver(lines) => write!(f, "game over: {}", lines.join(" ")),
+            ExploreError::NoCheckpoint => write!(f, "no pressure-sensitive floor found"),
+            ExploreError::NoCombination => write!(f, "no combination of items is accepted"),
+        }
+    }
+}
+
+impl Error for ExploreError {}
+
+/// What the explorer found and did.
+#[derive(Debug, Default, PartialEq)]
+pub struct Exploration {
+    pub rooms: BTreeMap<String, Room>,
+    /// Items picked up, in the order they were found.
+    pub items: Vec<String>,
+    /// Items that ended the game, hung it or stopped the droid moving.
+    pub unsafe_items: Vec<String>,
+    /// The items the pressure plate accepted.
+    pub combination: Vec<String>,
+    /// What the game printed once it let the droid through.
+    pub ending: Vec<String>,
+}
+
+struct Explorer<'a, G: Game> {
+    game: &'a mut G,
+    found: Exploration,
+    // room next to the pressure plate and the door leading onto it
+    checkpoint: Option<(Vec<String>, String)>,
+}
+
+/// Maps every room reachable from `start`, the output describing the room
+/// the droid is in, and picks up every item that is safe to carry. Then it
+/// walks to the security checkpoint and tries combinations of the items on
+/// the pressure plate until one lets the droid through.
+pub fn explore<G: Game>(game: &mut G, start: &[String]) -> Result<Exploration, ExploreError> {
+    let room = parse_room(start).ok_or(ExploreError::NotARoom)?;
+    let mut explorer = Explorer {
+        game,
+        found: Exploration::default(),
+        checkpoint: None,
+    };
+
+    explorer.visit(room, &mut vec![])?;
+
+    let (path, door) = explorer
+        .checkpoint
+        .take()
+        .ok_or(ExploreError::NoCheckpoint)?;
+    for step in &path {
+        explorer.send(step)?;
+    }
+    explorer.try_combinations(&door)?;
+
+    Ok(explorer.found)
+}
+
+impl<'a, G: Game> Explorer<'a, G> {
+    fn send(&mut self, command: &str) -> Result<Vec<String>, ExploreError> {
+        let lines = self.game.send(command)?;
+        if self.game.is_over() {
+            return Err(ExploreError::GameOver(lines));
+        }
+
+        Ok(lines)
+    }
+
+    // depth-first, always coming back the way it went in
+    fn visit(&mut self, room: Room, path: &mut Vec<String>) -> Result<(), ExploreError> {
+        self.found.rooms.insert(room.name.clone(), room.clone());
+
+        // without a door to walk through there is no telling whether an
+        // item is safe, so those are left where they are
+        if let Some(door) = room.doors.first() {
+            for item in &room.items {
+                if self.is_safe(item, door) {
+                    self.send(&format!("take {}", item))?;
+                    self.found.items.push(item.clone());
+                } else {
+                    self.found.unsafe_items.push(item.clone());
+                }
+            }
+        }
+
+        for door in &room.doors {
+            if path.last().map(|last| opposite(last)) == Some(door.as_str()) {
+                continue;
+            }
+
+            let next = match parse_room(&self.send(door)?) {
+                Some(next) => next,
+                None => continue,
+            };
+
+            if next.name == room.name {
+                // the pressure plate threw the droid back
+                self.checkpoint = Some((path.clone(), door.clone()));
+                continue;
+            }
+
+            if !self.found.rooms.contains_key(&next.name) {
+                path.push(door.clone());
+                self.visit(next, path)?;
+                path.pop();
+            }
+
+            self.send(opposite(door))?;
+        }
+
+        Ok(())
+    }
+
+    // takes the item on a fork of the game and checks the droid can still
+    // walk through a door afterwards
+    fn is_safe(&self, item: &str, door: &str) -> bool {
+        let mut trial = self.game.fork();
+        for command in &[format!("take {}", item), String::from(door)] {
+            match trial.send(command) {
+                Ok(_) if trial.is_over() => return false,
+                Ok(lines) if command == door => return parse_room(&lines).is_some(),
+                Ok(_) => (),
+                Err(_) => return false,
+            }
+        }
+
+        false
+    }
+
+    // walks every subset of the items in Gray code order, so each attempt
+    // only takes or drops a single item
+    fn try_combinations(&mut self, door: &str) -> Result<(), ExploreError> {
+        let items = self.found.items.clone();
+        for item in &items {
+            self.send(&format!("drop {}", item))?;
+        }
+
+        let mut held = 0usize;
+        for i in 0..1usize << items.len() {
+            let wanted = i ^ (i >> 1);
+            let changed = held ^ wanted;
+            if changed != 0 {
+                let item = &items[changed.trailing_zeros() as usize];
+                let verb = if wanted & changed != 0 {
+                    "take"
+                } else {
+                    "drop"
+                };
+                self.send(&format!("{} {}", verb, item))?;
+                held = wanted;
+            }
+
+            let lines = self.game.send(door)?;
+            if self.game.is_over() {
+                self.found.combination = (0..items.len())
+                    .filter(|bit| held & (1 << bit) != 0)
+                    .map(|bit| items[bit].clone())
+                    .collect();
+                self.found.ending = lines;
+                return Ok(());
+            }
+        }
+
+        Err(ExploreError::NoCombination)
+    }
+}
+
+/// Plays an ASCII adventure interactively. Lines read from `input` go to the
+/// game as they are, except for:
+///
+/// - `n`, `s`, `e`, `w` and `i` as shorthands for the directions and `inv`
+/// - `!!` to repeat the previous command
+/// - `!save <name>` and `!load <name>` to snapshot the game and go back to it
+/// - `!checkpoints` to list the saved snapshots
+/// - `!write <path>` to save the game to a snapshot file
+/// - `!explore` to let the explorer map the ship and get through security,
+///   looking around first if the room wasn't described yet, as in a resumed
+///   game
+/// - `!quit` to stop playing
+pub fn play(
+    mut computer: AsciiComputer,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    // the room description is kept with each snapshot for `!explore` to start from
+    let mut checkpoints: HashMap<String, (Snapshot, Vec<String>)> = HashMap::new();
+    let mut previous = String::new();
+    let mut last_output: Vec<String> = match computer.run() {
+        Ok(lines) => lines.into_iter().map(to_text).collect(),
+        Err(err) => return writeln!(out, "{}", err),
+    };
+    print_lines(&last_output, out)?;
+
+    loop {
+        out.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        let mut command = String::from(line.trim());
+        if command == "!!" {
+            command = previous.clone();
+        }
+        previous = command.clone();
+
+        let words: Vec<&str> = command.split_whitespace().collect();
+        match words.as_slice() {
+            [] => (),
+            ["!quit"] => return Ok(()),
+            ["!save", name] => {
+                let snapshot = computer.interpreter().snapshot();
+                checkpoints.insert(String::from(*name), (snapshot, last_output.clone()));
+                writeln!(out, "saved checkpoint {}", name)?;
+            }
+            ["!load", name] => match checkpoints.get(*name) {
+                Some((snapshot, room)) => {
+                    computer = AsciiComputer::new(Interpreter::from_snapshot(snapshot));
+                    last_output = room.clone();
+                    writeln!(out, "loaded checkpoint {}", name)?;
+                }
+                None => writeln!(out, "no checkpoint named {}", name)?,
+            },
+            ["!checkpoints"] => {
+                let mut names: Vec<&String> = checkpoints.keys().collect();
+                names.sort();
+                for name in names {
+                    writeln!(out, "{}", name)?;
+                }
+            }
+            ["!write", path] => match computer.interpreter().snapshot().save(path) {
+                Ok(()) => writeln!(out, "wrote {}", path)?,
+                Err(err) => writeln!(out, "{}", err)?,
+            },
+            ["!explore"] => {
+                if parse_room(&last_output).is_none() {
+                    last_output = look_around(&computer).unwrap_or_default();
+                }
+
+                match explore(&mut computer, &last_output) {
+                    Ok(found) => {
+                        writeln!(out, "explored {} rooms", found.rooms.len())?;
+                        writeln!(out, "unsafe items: {}", found.unsafe_items.join(", "))?;
+                        writeln!(out, "combination: {}", found.combination.join(", "))?;
+                        print_lines(&found.ending, out)?;
+                    }
+                    Err(err) => writeln!(out, "{}", err)?,
+                }
+            }
+            _ if command.starts_with('!') => writeln!(out, "unknown command {}", command)?,
+            _ if computer.is_halted() => {
+                writeln!(out, "the game is over, !load a checkpoint or !quit")?
+            }
+            _ => {
+                let command = match command.as_str() {
+                    "n" => "north",
+                    "s" => "south",
+                    "e" => "east",
+                    "w" => "west",
+                    "i" => "inv",
+                    other => other,
+                };
+
+                match computer.send(command) {
+                    Ok(lines) => {
+                        print_lines(&lines, out)?;
+                        if parse_room(&lines).is_some() {
+                            last_output = lines;
+                        }
+                    }
+                    Err(err) => writeln!(out, "{}", err)?,
+                }
+            }
+        }
+    }
+}
+
+fn print_lines(lines: &[String], out: &mut dyn Write) -> io::Result<()> {
+    for line in lines {
+        writeln!(out, "{}", line)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::error::ErrorKind;
+    use super::*;
+    use std::collections::BTreeSet;
+
+    // a scripted stand-in for the droid adventure:
+    //
+    //   Kitchen - Hull Breach - Hallway - Security Checkpoint - [floor]
+    //                 |
+    //              Reactor
+    #[derive(Clone)]
+    struct Ship {
+        here: &'static str,
+        items: HashMap<&'static str, Vec<&'static str>>,
+        inventory: BTreeSet<&'static str>,
+        over: bool,
+        stuck: bool,
+    }
+
+    const DOORS: [(&str, &str, &str); 5] = [
+        ("Hull Breach", "west", "Kitchen"),
+        ("Hull Breach", "east", "Hallway"),
+        ("Hull Breach", "south", "Reactor"),
+        ("Hallway", "east", "Security Checkpoint"),
+        ("Security Checkpoint", "east", "Pressure-Sensitive Floor"),
+    ];
+
+    const ITEMS: [&str; 3] = ["mug", "wreath", "sand"];
+
+    impl Ship {
+        fn new() -> Ship {
+            let mut items = HashMap::new();
+            items.insert("Kitchen", vec!["mug", "giant electromagnet"]);
+            items.insert("Reactor", vec!["photons", "infinite loop", "wreath"]);
+            items.insert("Hallway", vec!["sand"]);
+
+            Ship {
+                here: "Hull Breach",
+                items,
+                inventory: BTreeSet::new(),
+                over: false,
+                stuck: false,
+            }
+        }
+
+        fn describe(&self, room: &str) -> Vec<String> {
+            let mut lines = vec![format!("== {} ==", room), String::new()];
+            lines.push(String::from("Doors here lead:"));
+            for &(from, door, to) in DOORS.iter() {
+                if from == room {
+                    lines.push(format!("- {}", door));
+                } else if to == room {
+                    lines.push(format!("- {}", opposite(door)));
+                }
+            }
+            if let Some(items) = self.items.get(room).filter(|items| !items.is_empty()) {
+                lines.push(String::new());
+                lines.push(String::from("Items here:"));
+                lines.extend(items.iter().map(|item| format!("- {}", item)));
+            }
+            lines.push(String::new());
+            lines.push(String::from("Command?"));
+            lines
+        }
+
+        fn go(&mut self, door: &str) -> Vec<String> {
+            if self.stuck {
+                return vec![String::from("The giant electromagnet is stuck to you.")];
+            }
+
+            let next = DOORS.iter().find_map(|&(from, d, to)| {
+                if from == self.here && d == door {
+                    Some(to)
+                } else if to == self.here && opposite(d) == door {
+                    Some(from)
+                } else {
+                    None
+                }
+            });
+
+            match next {
+                Some("Pressure-Sensitive Floor") => {
+                    let wanted: BTreeSet<&str> = vec!["mug", "wreath"].into_iter().collect();
+                    if self.inventory == wanted {
+                        self.over = true;
+                        vec![String::from("You may proceed. The code is 1234.")]
+                    } else {
+                        let mut lines = vec![
+                            String::from("== Pressure-Sensitive Floor =="),
+                            String::from("Alert! You are being ejected."),
+                        ];
+                        lines.extend(self.describe(self.here));
+                        lines
+                    }
+                }
+                Some(room) => {
+                    self.here = room;
+                    self.describe(room)
+                }
+                None => vec![String::from("You can't go that way.")],
+            }
+        }
+    }
+
+    impl Game for Ship {
+        fn send(&mut self, command: &str) -> Result<Vec<String>, IntcodeError> {
+            if let Some(item) = command.strip_prefix("take ") {
+                self.items
+                    .entry(self.here)
+                    .or_default()
+                    .retain(|i| *i != item);
+                match item {
+                    "photons" => self.over = true,
+                    "giant electromagnet" => self.stuck = true,
+                    "infinite loop" => {
+                        return Err(IntcodeError::new(0, 0, ErrorKind::BudgetExhausted))
+                    }
+                    _ => (),
+                }
+                self.inventory.extend(ITEMS.iter().find(|i| **i == item));
+                return Ok(vec![]);
+            }
+
+            if let Some(item) = command.strip_prefix("drop ") {
+                self.inventory.retain(|i| *i != item);
+                let dropped = ITEMS.iter().find(|i| **i == item);
+                self.items.entry(self.here).or_default().extend(dropped);
+                return Ok(vec![]);
+            }
+
+            Ok(self.go(command))
+        }
+
+        fn is_over(&self) -> bool {
+            self.over
+        }
+
+        fn fork(&self) -> Ship {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_parse_room() {
+        let lines: Vec<String> = vec![
+            "== Pressure-Sensitive Floor ==",
+            "Alert!",
+            "",
+            "== Hull Breach ==",
+            "You got in through a hole in the floor here.",
+            "",
+            "Doors here lead:",
+            "- north",
+            "- east",
+            "",
+            "Items here:",
+            "- mug",
+            "",
+            "Command?",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+        assert_eq!(
+            parse_room(&lines),
+            Some(Room {
+                name: String::from("Hull Breach"),
+                doors: vec![String::from("north"), String::from("east")],
+                items: vec![String::from("mug")],
+            })
+        );
+        assert_eq!(parse_room(&lines[1..3]), None);
+    }
+
+    #[test]
+    fn test_explore() {
+        let mut ship = Ship::new();
+        let start = ship.describe("Hull Breach");
+        let found = explore(&mut ship, &start).unwrap();
+
+        assert_eq!(found.rooms.len(), 5);
+        assert_eq!(found.items, vec!["mug", "sand", "wreath"]);
+        assert_eq!(
+            found.unsafe_items,
+            vec!["giant electromagnet", "photons", "infinite loop"]
+        );
+        assert_eq!(found.combination, vec!["mug", "wreath"]);
+        assert_eq!(found.ending, vec!["You may proceed. The code is 1234."]);
+    }
+
+    #[test]
+    fn test_explore_room_without_doors() {
+        let mut ship = Ship::new();
+        ship.here = "Closet";
+        ship.items.insert("Closet", vec!["mug"]);
+        let start = ship.describe("Closet");
+
+        assert_eq!(explore(&mut ship, &start), Err(ExploreError::NoCheckpoint));
+        assert!(ship.inventory.is_empty());
+    }
+
+    #[test]
+    fn test_look_around() {
+        let mut ship = Ship::new();
+        ship.send("east").unwrap();
+        let room = parse_room(&look_around(&ship).unwrap()).unwrap();
+        assert_eq!(room.name, "Hallway");
+        assert_eq!(ship.here, "Hallway");
+
+        // the first door tried leads onto the pressure plate
+        ship.send("east").unwrap();
+        let room = parse_room(&look_around(&ship).unwrap()).unwrap();
+        assert_eq!(room.name, "Security Checkpoint");
+    }
+
+    // stands in one room, answering every line with the same reply
+    fn one_room_game() -> AsciiComputer {
+        let print = |text: &str| -> Vec<isize> {
+            text.bytes().flat_map(|c| vec![104, c as isize]).collect()
+        };
+        let mut src = print("== Hull Breach ==\n\nDoors here lead:\n- north\n\nCommand?\n");
+        let read = src.len() as isize;
+        let reply = print("You can't go that way.\n");
+        let c = read + 9 + reply.len() as isize + 3;
+        // reads characters up to a newline, then replies
+        src.extend(&[3, c, 1008, c, 10, c + 1, 1006, c + 1, read]);
+        src.extend(reply);
+        src.extend(&[1105, 1, read, 0, 0]);
+
+        AsciiComputer::new(Interpreter::from_bytecode(&src))
+    }
+
+    #[test]
+    fn test_play() {
+        let lines = [
+            "n",
+            "!save start",
+            "look",
+            "!!",
+            "!checkpoints",
+            "!load nowhere",
+            "!load start",
+            "!bogus",
+            "!quit",
+            "north",
+        ];
+        let text = lines.join("\n");
+        let mut input = text.as_bytes();
+        let mut out = vec![];
+        play(one_room_game(), &mut input, &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "== Hull Breach ==\n\nDoors here lead:\n- north\n\nCommand?\n",
+                "You can't go that way.\n",
+                "saved checkpoint start\n",
+                "You can't go that way.\n",
+                "You can't go that way.\n",
+                "start\n",
+                "no checkpoint named nowhere\n",
+                "loaded checkpoint start\n",
+                "unknown command !bogus\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use super::error::{ErrorKind, IntcodeError};
+use super::interpreter::{Interpreter, Status};
+
+/// Copies of one program wired in series: every amplifier gets its phase
+/// setting as its first input, then the previous amplifier's output as its
+/// next one.
+///
+/// Without feedback every amplifier only runs until its first output. In a
+/// feedback loop the last amplifier feeds the first again, pass after pass,
+/// until a pass in which an amplifier halts.
+pub struct AmplifierChain {
+    amplifiers: Vec<Interpreter>,
+    feedback: bool,
+}
+
+impl AmplifierChain {
+    pub fn new(program: &[isize], phases: &[isize]) -> AmplifierChain {
+        AmplifierChain::build(program, phases, false)
+    }
+
+    pub fn feedback_loop(program: &[isize], phases: &[isize]) -> AmplifierChain {
+        AmplifierChain::build(program, phases, true)
+    }
+
+    fn build(program: &[isize], phases: &[isize], feedback: bool) -> AmplifierChain {
+        let amplifiers = phases
+            .iter()
+            .map(|&phase| {
+                let mut amplifier = Interpreter::from_bytecode(program);
+                amplifier.push_input(phase);
+                amplifier
+            })
+            .collect();
+
+        AmplifierChain {
+            amplifiers,
+            feedback,
+        }
+    }
+
+    /// Feeds `signal` into the first amplifier and returns the last signal
+    /// the final amplifier produced. A chain without amplifiers passes
+    /// `signal` straight through.
+    pub fn run(&mut self, mut signal: isize) -> Result<isize, IntcodeError> {
+        if self.amplifiers.is_empty() {
+            return Ok(signal);
+        }
+
+        loop {
+            let mut halted = false;
+
+            for amplifier in &mut self.amplifiers {
+                amplifier.push_input(signal);
+                match amplifier.run()? {
+                    Status::Output(val) => signal = val,
+                    Status::Halted => halted = true,
+                    // every amplifier reads exactly one signal per pass
+                    Status::NeedsInput => {
+                        let ip = amplifier.instruction_pointer();
+                        return Err(IntcodeError::new(
+                            ip,
+                            amplifier.read(ip),
+                            ErrorKind::InputExhausted,
+                        ));
+                    }
+                }
+            }
+
+            if halted || !self.feedback {
+                return Ok(signal);
+            }
+        }
+    }
+}
+
+/// Tries every ordering of `phases` and returns the highest final signal for
+/// an input signal of 0, along with the phase setting that produced it.
+pub fn max_signal(
+    program: &[isize],
+    phases: &[isize],
+    feedback: bool,
+) -> Result<(isize, Vec<isize>), IntcodeError> {
+    let mut best: Option<(isize, Vec<isize>)> = None;
+
+    for setting in permutations(phases) {
+        let signal = AmplifierChain::build(program, &setting, feedback).run(0)?;
+        match best {
+            Some((max, _)) if max >= signal => (),
+            _ => best = Some((signal, setting)),
+        }
+    }
+
+    Ok(best.unwrap_or((0, vec![])))
+}
+
+pub fn permutations(items: &[isize]) -> Vec<Vec<isize>> {
+    if items.len() <= 1 {
+        return vec![items.to_vec()];
+    }
+
+    let mut result = vec![];
+    for i in 0..items.len() {
+        let mut rest = items.to_vec();
+        let first = rest.remove(i);
+        for mut permutation in permutations(&rest) {
+            permutation.insert(0, first);
+            result.push(permutation);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain() {
+        let program = vec![
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        assert_eq!(
+            AmplifierChain::new(&program, &[4, 3, 2, 1, 0]).run(0),
+            Ok(43210)
+        );
+        assert_eq!(
+            max_signal(&program, &[0, 1, 2, 3, 4], false),
+            Ok((43210, vec![4, 3, 2, 1, 0]))
+        );
+    }
+
+    #[test]
+    fn test_feedback_loop() {
+        let program = vec![
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        assert_eq!(
+            AmplifierChain::feedback_loop(&program, &[9, 8, 7, 6, 5]).run(0),
+            Ok(139629729)
+        );
+        assert_eq!(
+            max_signal(&program, &[5, 6, 7, 8, 9], true),
+            Ok((139629729, vec![9, 8, 7, 6, 5]))
+        );
+    }
+
+    #[test]
+    fn test_no_amplifiers() {
+        let program = vec![3, 0, 4, 0, 99];
+        assert_eq!(AmplifierChain::new(&program, &[]).run(7), Ok(7));
+        assert_eq!(AmplifierChain::feedback_loop(&program, &[]).run(7), Ok(7));
+        assert_eq!(max_signal(&program, &[], true), Ok((0, vec![])));
+    }
+
+    #[test]
+    fn test_permutations() {
+        assert_eq!(
+            permutations(&[1, 2, 3]),
+            vec![
+                vec![1, 2, 3],
+                vec![1, 3, 2],
+                vec![2, 1, 3],
+                vec![2, 3, 1],
+                vec![3, 1, 2],
+                vec![3, 2, 1],
+            ]
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::prelude::*;
+use std::io::stdin;
+
+use super::error::IntcodeError;
+use super::interpreter::{Interpreter, Status};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tile {
+    Empty,
+    Wall,
+    Block,
+    Paddle,
+    Ball,
+}
+
+impl Tile {
+    pub fn from_id(id: isize) -> Option<Tile> {
+        match id {
+            0 => Some(Tile::Empty),
+            1 => Some(Tile::Wall),
+            2 => Some(Tile::Block),
+            3 => Some(Tile::Paddle),
+            4 => Some(Tile::Ball),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(self) -> char {
+        match self {
+            Tile::Empty => ' ',
+            Tile::Wall => '#',
+            Tile::Block => '=',
+            Tile::Paddle => '_',
+            Tile::Ball => 'o',
+        }
+    }
+
+    /// RGB colour of the tile in image frames.
+    pub fn color(self) -> [u8; 3] {
+        match self {
+            Tile::Empty => [0, 0, 0],
+            Tile::Wall => [128, 128, 128],
+            Tile::Block => [200, 80, 40],
+            Tile::Paddle => [240, 240, 240],
+            Tile::Ball => [80, 200, 240],
+        }
+    }
+}
+
+/// The cabinet's screen: every tile drawn so far and the last score shown.
+#[derive(Debug, Default)]
+pub struct Screen {
+    tiles: HashMap<(usize, usize), Tile>,
+    width: usize,
+    height: usize,
+    score: isize,
+}
+
+impl Screen {
+    /// Applies one output triple. `(-1, 0, score)` shows a score, anything
+    /// else draws the tile `id` at `(x, y)`; ids the cabinet doesn't know and
+    /// positions off the screen are ignored.
+    pub fn draw(&mut self, x: isize, y: isize, id: isize) {
+        if (x, y) == (-1, 0) {
+            self.score = id;
+            return;
+        }
+
+        if let (Some(tile), true) = (Tile::from_id(id), x >= 0 && y >= 0) {
+            let (x, y) = (x as usize, y as usize);
+            self.tiles.insert((x, y), tile);
+            self.width = self.width.max(x + 1);
+            self.height = self.height.max(y + 1);
+        }
+    }
+
+    pub fn tile_at(&self, x: usize, y: usize) -> Tile {
+        self.tiles.get(&(x, y)).cloned().unwrap_or(Tile::Empty)
+    }
+
+    pub fn score(&self) -> isize {
+        self.score
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn count(&self, tile: Tile) -> usize {
+        self.tiles.values().filter(|&&t| t == tile).count()
+    }
+
+    /// Position of some tile of the given kind, meant for the ball and the
+    /// paddle which only ever appear once.
+    pub fn find(&self, tile: Tile) -> Option<(usize, usize)> {
+        self.tiles
+            .iter()
+            .find(|(_, &t)| t == tile)
+            .map(|(&pos, _)| pos)
+    }
+
+    /// The screen as text, one row per line, followed by the score.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                text.push(self.tile_at(x, y).symbol());
+            }
+            text.push('\n');
+        }
+        text.push_str(&format!("score: {}\n", self.score));
+        text
+    }
+
+    /// Writes the screen as a binary PPM image with every tile `scale`
+    /// pixels wide and tall.
+    pub fn write_ppm(&self, out: &mut dyn Write, scale: usize) -> io::Result<()> {
+        writeln!(out, "P6")?;
+        writeln!(out, "{} {}", self.width * scale, self.height * scale)?;
+        writeln!(out, "255")?;
+
+        let mut row = Vec::with_capacity(self.width * scale * 3);
+        for y in 0..self.height {
+            row.clear();
+            for x in 0..self.width {
+                let color = self.tile_at(x, y).color();
+                for _ in 0..scale {
+                    row.extend_from_slice(&color);
+                }
+            }
+            for _ in 0..scale {
+                out.write_all(&row)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Decides where the joystick points every time the game asks: `-1` for
+/// left, `0` for neutral and `1` for right.
+pub trait Joystick {
+    fn tilt(&mut self, screen: &Screen) -> isize;
+}
+
+pub struct Neutral;
+
+impl Joystick for Neutral {
+    fn tilt(&mut self, _: &Screen) -> isize {
+        0
+    }
+}
+
+/// Keeps the paddle under the ball.
+pub struct AutoPlayer;
+
+impl Joystick for AutoPlayer {
+    fn tilt(&mut self, screen: &Screen) -> isize {
+        match (screen.find(Tile::Ball), screen.find(Tile::Paddle)) {
+            (Some((ball, _)), Some((paddle, _))) => (ball as isize - paddle as isize).signum(),
+            _ => 0,
+        }
+    }
+}
+
+/// Reads a line from the terminal for every move: `a` tilts left, `d` tilts
+/// right and anything else leaves the joystick neutral.
+pub struct StdinJoystick;
+
+impl Joystick for StdinJoystick {
+    fn tilt(&mut self, _: &Screen) -> isize {
+        let mut line = String::new();
+        if stdin().read_line(&mut line).is_err() {
+            return 0;
+        }
+
+        match line.trim() {
+            "a" => -1,
+            "d" => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// An arcade cabinet program hooked up to a screen.
+pub struct Arcade {
+    interpreter: Interpreter,
+    screen: Screen,
+    pending: Vec<isize>,
+}
+
+impl Arcade {
+    pub fn new(interpreter: Interpreter) -> Arcade {
+        Arcade {
+            interpreter,
+            screen: Screen::default(),
+            pending: vec![],
+        }
+    }
+
+    /// Sets the cabinet to free play, the 2019 puzzle's way of starting a
+    /// game that reads the joystick.
+    pub fn insert_quarters(&mut self) {
+        // address 0 is always within the memory limit
+        self.interpreter.write(0, 2).unwrap();
+    }
+
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    /// Runs the game to the end, asking `joystick` for a move whenever the
+    /// program reads input. `on_frame` sees the screen before every move and
+    /// once more when the game halts. Returns the final score.
+    pub fn run(
+        &mut self,
+        joystick: &mut dyn Joystick,
+        on_frame: &mut dyn FnMut(&Screen),
+    ) -> Result<isize, IntcodeError> {
+        loop {
+            match self.interpreter.run()? {
+                Status::Output(val) => {
+                    self.pending.push(val);
+                    if let [x, y, id] = self.pending[..] {
+                        self.screen.draw(x, y, id);
+                        self.pending.clear();
+                    }
+                }
+                Status::NeedsInput => {
+                    on_frame(&self.screen);
+                    let tilt = joystick.tilt(&self.screen);
+                    self.interpreter.push_input(tilt);
+                }
+                Status::Halted => {
+                    on_frame(&self.screen);
+                    return Ok(self.screen.score());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::assembler::assemble;
+    use super::*;
+
+    fn demo() -> Arcade {
+        // draws a wall, paddle and ball, then shows the joystick as the score
+        let src = assemble(
+            "
+                    out #0
+                    out #0
+                    out #1
+                    out #1
+                    out #1
+                    out #3
+                    out #3
+                    out #0
+                    out #4
+                    in [tilt]
+                    out #-1
+                    out #0
+                    out [tilt]
+                    hlt
+            tilt:   data 0
+            ",
+        )
+        .unwrap();
+
+        Arcade::new(Interpreter::from_bytecode(&src))
+    }
+
+    #[test]
+    fn test_screen() {
+        let mut arcade = demo();
+        let mut frames = vec![];
+        let score = arcade.run(&mut AutoPlayer, &mut |screen| frames.push(screen.render()));
+
+        assert_eq!(score, Ok(1));
+        assert_eq!(
+            frames,
+            vec!["#  o\n _  \nscore: 0\n", "#  o\n _  \nscore: 1\n"]
+        );
+        assert_eq!(arcade.screen().count(Tile::Wall), 1);
+        assert_eq!(arcade.screen().find(Tile::Ball), Some((3, 0)));
+    }
+
+    #[test]
+    fn test_neutral_joystick() {
+        assert_eq!(demo().run(&mut Neutral, &mut |_| ()), Ok(0));
+    }
+
+    #[test]
+    fn test_write_ppm() {
+        let mut screen = Screen::default();
+        screen.draw(1, 0, 1);
+
+        let mut image = vec![];
+        screen.write_ppm(&mut image, 2).unwrap();
+
+        let mut expected = b"P6\n4 2\n255\n".to_vec();
+        for _ in 0..2 {
+            expected.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+            expected.extend_from_slice(&[128, 128, 128, 128, 128, 128]);
+        }
+        assert_eq!(image, expected);
+    }
+}
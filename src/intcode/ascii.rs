@@ -0,0 +1,194 @@
+use std::collections::VecDeque;
+use std::io::prelude::*;
+use std::io::{stdin, stdout};
+
+use super::error::IntcodeError;
+use super::interpreter::{Interpreter, Status};
+use super::io::{InputSource, OutputSink};
+
+/// What an ASCII program printed: text, or a value outside the ASCII range
+/// that has no business being decoded as a character.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AsciiOutput {
+    /// A line of text without its trailing newline. Text printed right
+    /// before the program paused for input comes through as a line too,
+    /// even without a newline.
+    Line(String),
+    Value(isize),
+}
+
+/// Talks text to an intcode program: strings go in as one word per
+/// character, output words come back as lines of text.
+pub struct AsciiComputer {
+    interpreter: Interpreter,
+    line: String,
+    halted: bool,
+}
+
+impl AsciiComputer {
+    pub fn new(interpreter: Interpreter) -> AsciiComputer {
+        AsciiComputer {
+            interpreter,
+            line: String::new(),
+            halted: false,
+        }
+    }
+
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+
+    pub fn interpreter_mut(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+
+    /// Queues `line` followed by a newline. Characters go in as their
+    /// Unicode code points, so anything outside ASCII is sent as is, as a
+    /// value above 127, for the program to deal with.
+    pub fn send_line(&mut self, line: &str) {
+        for c in line.chars() {
+            self.interpreter.push_input(c as isize);
+        }
+        self.interpreter.push_input('\n' as isize);
+    }
+
+    /// Runs until the program wants input it hasn't been sent yet or halts,
+    /// returning everything it printed on the way.
+    pub fn run(&mut self) -> Result<Vec<AsciiOutput>, IntcodeError> {
+        let mut output = vec![];
+
+        loop {
+            match self.interpreter.run()? {
+                Status::Output(val) => match decode(val) {
+                    Some('\n') => output.push(AsciiOutput::Line(self.line.split_off(0))),
+                    Some(c) => self.line.push(c),
+                    None => {
+                        self.flush_line(&mut output);
+                        output.push(AsciiOutput::Value(val));
+                    }
+                },
+                Status::NeedsInput => break,
+                Status::Halted => {
+                    self.halted = true;
+                    break;
+                }
+            }
+        }
+
+        self.flush_line(&mut output);
+        Ok(output)
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    fn flush_line(&mut self, output: &mut Vec<AsciiOutput>) {
+        if !self.line.is_empty() {
+            output.push(AsciiOutput::Line(self.line.split_off(0)));
+        }
+    }
+}
+
+fn decode(val: isize) -> Option<char> {
+    if (0..=127).contains(&val) {
+        Some(val as u8 as char)
+    } else {
+        None
+    }
+}
+
+/// Reads input a line at a time from the terminal and feeds it to the
+/// program one character at a time, newline included. Like
+/// [`AsciiComputer::send_line`] it sends characters outside ASCII as is.
+#[derive(Default)]
+pub struct AsciiStdinInput {
+    buffer: VecDeque<isize>,
+}
+
+impl InputSource for AsciiStdinInput {
+    fn read_input(&mut self) -> Option<isize> {
+        if self.buffer.is_empty() {
+            let mut line = String::new();
+            match stdin().read_line(&mut line) {
+                Ok(0) | Err(_) => return None,
+                Ok(_) => self.buffer.extend(line.chars().map(|c| c as isize)),
+            }
+        }
+
+        self.buffer.pop_front()
+    }
+}
+
+/// Prints ASCII output as text and anything else as a number on its own
+/// line.
+pub struct AsciiStdoutOutput;
+
+impl OutputSink for AsciiStdoutOutput {
+    fn write_output(&mut self, val: isize) {
+        let mut out = stdout();
+        let written = match decode(val) {
+            Some(c) => write!(out, "{}", c),
+            None => writeln!(out, "\n{}", val),
+        };
+
+        if written.and_then(|_| out.flush()).is_ok() {
+            //
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::assembler::assemble;
+    use super::*;
+
+    #[test]
+    fn test_ascii_computer() {
+        // prints "Hi?" then echoes one input line back, then prints a big number
+        let src = assemble(
+            "
+                    out #72
+                    out #105
+                    out #10
+                    out #63
+            loop:   in [c]
+                    out [c]
+                    eq [c], #10, [done]
+                    jz [done], #loop
+                    out #1000
+                    hlt
+            c:      data 0
+            done:   data 0
+            ",
+        )
+        .unwrap();
+        let mut computer = AsciiComputer::new(Interpreter::from_bytecode(&src));
+
+        assert_eq!(
+            computer.run(),
+            Ok(vec![
+                AsciiOutput::Line(String::from("Hi")),
+                AsciiOutput::Line(String::from("?")),
+            ])
+        );
+        assert!(!computer.is_halted());
+
+        computer.send_line("north");
+        assert_eq!(
+            computer.run(),
+            Ok(vec![
+                AsciiOutput::Line(String::from("north")),
+                AsciiOutput::Value(1000),
+            ])
+        );
+        assert!(computer.is_halted());
+    }
+
+    #[test]
+    fn test_send_line_outside_ascii() {
+        let mut computer = AsciiComputer::new(Interpreter::from_bytecode(&[99]));
+        computer.send_line("né");
+        assert_eq!(computer.interpreter().snapshot().inputs, vec![110, 233, 10]);
+    }
+}
@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// Mnemonic, opcode, operand count and which operands (1-based) are written
+/// to. Mnemonics are the ones `Op::mnemonic` prints.
+const INSTRUCTIONS: [(&str, isize, usize, Option<usize>); 10] = [
+    ("add", 1, 3, Some(3)),
+    ("mul", 2, 3, Some(3)),
+    ("in", 3, 1, Some(1)),
+    ("out", 4, 1, None),
+    ("jnz", 5, 2, None),
+    ("jz", 6, 2, None),
+    ("lt", 7, 3, Some(3)),
+    ("eq", 8, 3, Some(3)),
+    ("arb", 9, 1, None),
+    ("hlt", 99, 0, None),
+];
+
+/// Where in the source an assembly error was found. Lines and columns are
+/// 1-based.
+#[derive(Debug, PartialEq)]
+pub struct AssembleError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for AssembleError {}
+
+// a number, or a label with an optional +/- offset
+struct Expr {
+    label: Option<String>,
+    offset: isize,
+    line: usize,
+    column: usize,
+}
+
+/// Assembles intcode source into bytecode that `Interpreter::from_bytecode`
+/// accepts.
+///
+/// Every line holds an optional `label:`, then an instruction or a `data`
+/// directive, then an optional `; comment`. Operands are `[address]` for
+/// position mode, `#value` for immediate mode and `rb+offset` for relative
+/// mode. Addresses and values can be numbers or labels, optionally with a
+/// constant offset such as `buffer+2`. `data` takes a comma-separated list
+/// of values that are copied into the program as they are.
+pub fn assemble(src: &str) -> Result<Vec<isize>, AssembleError> {
+    let mut labels: HashMap<String, isize> = HashMap::new();
+    let mut words: Vec<Expr> = vec![];
+
+    for (index, text) in src.lines().enumerate() {
+        let line = index + 1;
+        let code = match text.find(';') {
+            Some(comment) => &text[..comment],
+            None => text,
+        };
+
+        let mut column = 1;
+        let mut rest = code;
+
+        if let Some(colon) = code.find(':') {
+            let label = code[..colon].trim();
+            let label_column = column_of(code, 0);
+            if !is_label(label) {
+                return Err(error(
+                    line,
+                    label_column,
+                    format!("invalid label '{}'", label),
+                ));
+            }
+            if labels.contains_key(label) {
+                return Err(error(
+                    line,
+                    label_column,
+                    format!("duplicate label '{}'", label),
+                ));
+            }
+
+            labels.insert(String::from(label), words.len() as isize);
+            column += colon + 1;
+            rest = &code[colon + 1..];
+        }
+
+        let trimmed = rest.trim_start();
+        if trimmed.is_empty() {
+            continue;
+        }
+        column += rest.len() - trimmed.len();
+
+        let (mnemonic, operands) = match trimmed.find(char::is_whitespace) {
+            Some(end) => (&trimmed[..end], &trimmed[end..]),
+            None => (trimmed, ""),
+        };
+        let operands = split_operands(operands, column + mnemonic.len());
+
+        if mnemonic == "data" {
+            if operands.is_empty() {
+                return Err(error(
+                    line,
+                    column,
+                    String::from("data needs at least one value"),
+                ));
+            }
+            for (operand_column, operand) in operands {
+                words.push(parse_expr(operand, line, operand_column)?);
+            }
+            continue;
+        }
+
+        let (opcode, arity, written) = match INSTRUCTIONS.iter().find(|ins| ins.0 == mnemonic) {
+            Some(&(_, opcode, arity, written)) => (opcode, arity, written),
+            None => {
+                return Err(error(
+                    line,
+                    column,
+                    format!("unknown mnemonic '{}'", mnemonic),
+                ));
+            }
+        };
+
+        if operands.len() != arity {
+            return Err(error(
+                line,
+                column,
+                format!(
+                    "{} takes {} operands, found {}",
+                    mnemonic,
+                    arity,
+                    operands.len()
+                ),
+            ));
+        }
+
+        let mut opcode_word = opcode;
+        let mut params = vec![];
+        for (i, (operand_column, operand)) in operands.into_iter().enumerate() {
+            let (mode, expr) = parse_operand(operand, line, operand_column)?;
+            if mode == 1 && written == Some(i + 1) {
+                return Err(error(
+                    line,
+                    operand_column,
+                    format!(
+                        "operand {} of {} is written to and can't be immediate",
+                        i + 1,
+                        mnemonic
+                    ),
+                ));
+            }
+
+            opcode_word += mode * 10isize.pow(i as u32 + 2);
+            params.push(expr);
+        }
+
+        words.push(Expr {
+            label: None,
+            offset: opcode_word,
+            line,
+            column,
+        });
+        words.extend(params);
+    }
+
+    words
+        .into_iter()
+        .map(|word| match &word.label {
+            None => Ok(word.offset),
+            Some(label) => match labels.get(label) {
+                Some(at) => at.checked_add(word.offset).ok_or_else(|| {
+                    error(
+                        word.line,
+                        word.column,
+                        format!("offset {} from label '{}' overflows", word.offset, label),
+                    )
+                }),
+                None => Err(error(
+                    word.line,
+                    word.column,
+                    format!("undefined label '{}'", label),
+                )),
+            },
+        })
+        .collect()
+}
+
+fn error(line: usize, column: usize, message: String) -> AssembleError {
+    AssembleError {
+        line,
+        column,
+        message,
+    }
+}
+
+fn column_of(text: &str, offset: usize) -> usize {
+    offset + text.len() - text.trim_start().len() + 1
+}
+
+// splits a comma-separated operand list, keeping the column each operand
+// starts at
+fn split_operands(text: &str, start_column: usize) -> Vec<(usize, &str)> {
+    if text.trim().is_empty() {
+        return vec![];
+    }
+
+    let mut operands = vec![];
+    let mut offset = 0;
+    for operand in text.split(',') {
+        operands.push((
+            start_column + column_of(operand, offset) - 1,
+            operand.trim(),
+        ));
+        offset += operand.len() + 1;
+    }
+
+    operands
+}
+
+fn parse_operand(
+    operand: &str,
+    line: usize,
+    column: usize,
+) -> Result<(isize, Expr), AssembleError> {
+    if operand.starts_with('[') && operand.ends_with(']') {
+        Ok((
+            0,
+            parse_expr(&operand[1..operand.len() - 1], line, column + 1)?,
+        ))
+    } else if let Some(value) = operand.strip_prefix('#') {
+        Ok((1, parse_expr(value, line, column + 1)?))
+    } else if let Some(offset) = operand.strip_prefix("rb") {
+        let offset: String = offset.chars().filter(|c| !c.is_whitespace()).collect();
+        let offset = match offset.as_str() {
+            "" => Ok(0),
+            _ if offset.starts_with('+') => offset[1..].parse(),
+            _ => offset.parse(),
+        };
+
+        match offset {
+            Ok(offset) => Ok((
+                2,
+                Expr {
+                    label: None,
+                    offset,
+                    line,
+                    column,
+                },
+            )),
+            Err(_) => Err(error(
+                line,
+                column,
+                format!("invalid relative operand '{}'", operand),
+            )),
+        }
+    } else {
+        Err(error(
+            line,
+            column,
+            format!(
+                "expected [address], #value or rb+offset, found '{}'",
+                operand
+            ),
+        ))
+    }
+}
+
+fn parse_expr(text: &str, line: usize, column: usize) -> Result<Expr, AssembleError> {
+    let column = column_of(text, column - 1);
+    let text = text.trim();
+
+    if let Ok(offset) = text.parse() {
+        return Ok(Expr {
+            label: None,
+            offset,
+            line,
+            column,
+        });
+    }
+
+    let (label, offset) = match text.find(['+', '-']) {
+        Some(sign) => {
+            let offset: String = text[sign..]
+                .chars()
+                .filter(|c| !c.is_whitespace() && *c != '+')
+                .collect();
+            match offset.parse() {
+                Ok(offset) => (text[..sign].trim(), offset),
+                Err(_) => return Err(error(line, column, format!("invalid offset in '{}'", text))),
+            }
+        }
+        None => (text, 0),
+    };
+
+    if !is_label(label) {
+        return Err(error(
+            line,
+            column,
+            format!("expected a number or a label, found '{}'", text),
+        ));
+    }
+
+    Ok(Expr {
+        label: Some(String::from(label)),
+        offset,
+        line,
+        column,
+    })
+}
+
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::disassembler::{disassemble, Line};
+    use super::super::DOUBLER;
+    use super::*;
+
+    #[test]
+    fn test_assemble() {
+        let src = "
+            ; doubles every input until it reads a 0
+            loop:   in [x]
+                    jz [x], #end
+                    mul [x], #2, [x]
+                    out [x]     ; print it
+                    jnz #1, #loop
+            end:    hlt
+            x:      data 0
+        ";
+
+        assert_eq!(assemble(src), Ok(DOUBLER.to_vec()));
+    }
+
+    #[test]
+    fn test_assemble_relative_and_offsets() {
+        let src = "
+            arb #buffer
+            add rb+0, rb-0, rb+1
+            out [buffer+1]
+            hlt
+            buffer: data 21, 0, buffer
+        ";
+
+        assert_eq!(
+            assemble(src),
+            Ok(vec![109, 9, 22201, 0, 0, 1, 4, 10, 99, 21, 0, 9])
+        );
+    }
+
+    #[test]
+    fn test_assemble_round_trips_disassembly() {
+        let src = vec![
+            1002, 4, 3, 4, 33, 109, 19, 204, -34, 21107, 1, 2, 3, 1208, -1, 7, 0, 1105, 0, 5, 6, 1,
+            2, 99, 3, 7,
+        ];
+        let text: Vec<String> = disassemble(&src)
+            .into_iter()
+            .map(|line| match line {
+                Line::Instruction(_, op) => op.to_string(),
+                Line::Data(_, word) => format!("data {}", word),
+            })
+            .collect();
+
+        assert_eq!(assemble(&text.join("\n")), Ok(src));
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        let errors = vec![
+            ("  foo [1]", 1, 3, "unknown mnemonic 'foo'"),
+            ("add [1], #2", 1, 1, "add takes 3 operands, found 2"),
+            (
+                "hlt\nadd [1], #2, #3",
+                2,
+                14,
+                "operand 3 of add is written to and can't be immediate",
+            ),
+            (
+                "out   2",
+                1,
+                7,
+                "expected [address], #value or rb+offset, found '2'",
+            ),
+            ("jnz #1, #nowhere", 1, 10, "undefined label 'nowhere'"),
+            ("a: hlt\na: hlt", 2, 1, "duplicate label 'a'"),
+            ("9lives: hlt", 1, 1, "invalid label '9lives'"),
+            ("out rb+x", 1, 5, "invalid relative operand 'rb+x'"),
+            ("data", 1, 1, "data needs at least one value"),
+            (
+                "hlt\nx: out [x+9223372036854775807]",
+                2,
+                9,
+                "offset 9223372036854775807 from label 'x' overflows",
+            ),
+        ];
+
+        for (src, line, column, message) in errors {
+            assert_eq!(
+                assemble(src),
+                Err(AssembleError {
+                    line,
+                    column,
+                    message: String::from(message)
+                }),
+                "{}",
+                src
+            );
+        }
+    }
+}
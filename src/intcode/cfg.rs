@@ -0,0 +1,372 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::sync::Arc;
+
+use super::memory::{MemoryManager, ReadOnlyMemoryManager};
+use super::parser::{Op, Param, Parser};
+
+/// How control leaves a basic block.
+#[derive(Debug, PartialEq)]
+pub enum Exit {
+    /// Runs on into the block starting at the given address.
+    Next(usize),
+    /// Always jumps to the given address.
+    Jump(usize),
+    /// A conditional jump.
+    Branch {
+        taken: usize,
+        not_taken: usize,
+    },
+    /// A jump whose target is read from memory, which can't be worked out
+    /// without running the program. `not_taken` is where a conditional jump
+    /// goes otherwise.
+    Unresolved {
+        target: Param,
+        not_taken: Option<usize>,
+    },
+    Halt,
+    /// Control reaches a word that isn't a valid instruction, or lies
+    /// outside the program.
+    Invalid(usize),
+}
+
+/// A run of instructions that only ever executes from the top down.
+#[derive(Debug, PartialEq)]
+pub struct Block {
+    pub start: usize,
+    pub instructions: Vec<(usize, Op)>,
+    pub exit: Exit,
+}
+
+impl Block {
+    /// Addresses of the blocks control can continue to.
+    pub fn successors(&self) -> Vec<usize> {
+        Block::successors_of(&self.exit)
+    }
+
+    fn successors_of(exit: &Exit) -> Vec<usize> {
+        match *exit {
+            Exit::Next(at) | Exit::Jump(at) => vec![at],
+            Exit::Branch { taken, not_taken } => vec![taken, not_taken],
+            Exit::Unresolved { not_taken, .. } => not_taken.into_iter().collect(),
+            Exit::Halt | Exit::Invalid(_) => vec![],
+        }
+    }
+}
+
+/// The control-flow graph of everything reachable from address 0, found by
+/// decoding `src` with [`Parser`] and following every jump with an
+/// immediate target.
+///
+/// This only sees the program as it is loaded: code that rewrites itself
+/// may well take paths the graph doesn't show.
+#[derive(Debug, PartialEq)]
+pub struct Cfg {
+    pub blocks: BTreeMap<usize, Block>,
+}
+
+// how an instruction ends its block, if it does. Jumps that can never be
+// taken don't end anything.
+fn exit_of(at: usize, op: &Op) -> Option<Exit> {
+    let next = at + op.size();
+    let (test, target, jump_if) = match *op {
+        Op::Halt => return Some(Exit::Halt),
+        Op::JumpIfTrue(test, target) => (test, target, true),
+        Op::JumpIfFalse(test, target) => (test, target, false),
+        _ => return None,
+    };
+
+    let taken = match test {
+        Param::ImmediateMode(val) => Some((val != 0) == jump_if),
+        _ => None,
+    };
+    let not_taken = if taken == Some(true) {
+        None
+    } else {
+        Some(next)
+    };
+
+    Some(match (taken, target) {
+        (Some(false), _) => return None,
+        (Some(true), Param::ImmediateMode(ip)) if ip >= 0 => Exit::Jump(ip as usize),
+        (None, Param::ImmediateMode(ip)) if ip >= 0 => Exit::Branch {
+            taken: ip as usize,
+            not_taken: next,
+        },
+        // a negative target fails at run time, which is as good as unknown
+        (_, target) => Exit::Unresolved { target, not_taken },
+    })
+}
+
+pub fn control_flow_graph(src: &[isize]) -> Cfg {
+    let parser = Parser::new(Arc::new(MemoryManager::new(src)) as Arc<dyn ReadOnlyMemoryManager>);
+    let decode = |at: usize| match parser.parse_op(at) {
+        Ok(op) if at + op.size() <= src.len() => Some(op),
+        _ => None,
+    };
+
+    // find every reachable instruction and where blocks have to start
+    let mut leaders = BTreeSet::new();
+    let mut reached = BTreeSet::new();
+    let mut pending = vec![0];
+    leaders.insert(0);
+    while let Some(at) = pending.pop() {
+        if !reached.insert(at) {
+            continue;
+        }
+
+        if let Some(op) = decode(at) {
+            match exit_of(at, &op) {
+                Some(exit) => {
+                    let targets = Block::successors_of(&exit);
+                    leaders.extend(targets.iter().cloned());
+                    pending.extend(targets);
+                }
+                None => pending.push(at + op.size()),
+            }
+        }
+    }
+
+    let mut blocks = BTreeMap::new();
+    for &start in &leaders {
+        let mut instructions = vec![];
+        let mut at = start;
+        let exit = loop {
+            let op = match decode(at) {
+                Some(op) => op,
+                None => break Exit::Invalid(at),
+            };
+
+            let next = at + op.size();
+            let exit = match exit_of(at, &op) {
+                None if leaders.contains(&next) => Some(Exit::Next(next)),
+                exit => exit,
+            };
+
+            instructions.push((at, op));
+            match exit {
+                Some(exit) => break exit,
+                None => at = next,
+            }
+        };
+
+        blocks.insert(
+            start,
+            Block {
+                start,
+                instructions,
+                exit,
+            },
+        );
+    }
+
+    Cfg { blocks }
+}
+
+impl Cfg {
+    /// Every edge as `(from, to)` block addresses.
+    pub fn edges(&self) -> Vec<(usize, usize)> {
+        self.blocks
+            .values()
+            .flat_map(|block| {
+                block
+                    .successors()
+                    .into_iter()
+                    .map(move |to| (block.start, to))
+            })
+            .collect()
+    }
+
+    /// The graph in Graphviz DOT format, e.g. for `dot -Tsvg`. Halting
+    /// blocks are drawn with a double border and unresolved jumps as dashed
+    /// edges into a `?` node.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph cfg {\n");
+        dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for (at, op) in &block.instructions {
+                write!(label, "{}: {}\\l", at, op).unwrap();
+            }
+            if let Exit::Invalid(at) = block.exit {
+                write!(label, "{}: invalid\\l", at).unwrap();
+            }
+
+            let border = if block.exit == Exit::Halt {
+                ", peripheries=2"
+            } else {
+                ""
+            };
+            writeln!(dot, "    b{} [label=\"{}\"{}];", block.start, label, border).unwrap();
+
+            match block.exit {
+                Exit::Branch { taken, not_taken } => {
+                    writeln!(dot, "    b{} -> b{} [label=\"taken\"];", block.start, taken).unwrap();
+                    writeln!(dot, "    b{} -> b{};", block.start, not_taken).unwrap();
+                }
+                Exit::Unresolved { target, not_taken } => {
+                    writeln!(dot, "    u{} [label=\"?\", shape=diamond];", block.start).unwrap();
+                    writeln!(
+                        dot,
+                        "    b{} -> u{} [label=\"{}\", style=dashed];",
+                        block.start, block.start, target
+                    )
+                    .unwrap();
+                    if let Some(next) = not_taken {
+                        writeln!(dot, "    b{} -> b{};", block.start, next).unwrap();
+                    }
+                }
+                _ => {
+                    for to in block.successors() {
+                        writeln!(dot, "    b{} -> b{};", block.start, to).unwrap();
+                    }
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// A short text description of the graph, one line per block.
+    pub fn summary(&self) -> String {
+        let count = |f: &dyn Fn(&Exit) -> bool| self.blocks.values().filter(|b| f(&b.exit)).count();
+
+        let mut text = format!(
+            "{} blocks, {} edges, {} halts, {} unresolved jumps\n",
+            self.blocks.len(),
+            self.edges().len(),
+            count(&|exit| *exit == Exit::Halt),
+            count(&|exit| matches!(exit, Exit::Unresolved { .. })),
+        );
+
+        for block in self.blocks.values() {
+            let end = block
+                .instructions
+                .last()
+                .map(|(at, op)| at + op.size())
+                .unwrap_or(block.start);
+            write!(
+                text,
+                "{}..{}: {} instructions, ",
+                block.start,
+                end,
+                block.instructions.len()
+            )
+            .unwrap();
+
+            match &block.exit {
+                Exit::Next(at) => writeln!(text, "then {}", at),
+                Exit::Jump(at) => writeln!(text, "jumps to {}", at),
+                Exit::Branch { taken, not_taken } => {
+                    writeln!(text, "branches to {} or {}", taken, not_taken)
+                }
+                Exit::Unresolved {
+                    target,
+                    not_taken: Some(at),
+                } => writeln!(text, "branches to {} (unresolved) or {}", target, at),
+                Exit::Unresolved { target, .. } => {
+                    writeln!(text, "jumps to {} (unresolved)", target)
+                }
+                Exit::Halt => writeln!(text, "halts"),
+                Exit::Invalid(at) => writeln!(text, "runs into invalid code at {}", at),
+            }
+            .unwrap();
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::assembler::assemble;
+    use super::*;
+
+    fn countdown() -> Vec<isize> {
+        // prints n down to 1, then leaves through a jump table entry
+        assemble(
+            "
+                    in [n]
+            loop:   jz [n], #done
+                    out [n]
+                    add [n], #-1, [n]
+                    jnz #1, #loop
+            done:   jz [n], [ret]
+                    hlt
+            n:      data 0
+            ret:    data 17
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_control_flow_graph() {
+        let cfg = control_flow_graph(&countdown());
+
+        let exits: Vec<(usize, &Exit)> = cfg.blocks.values().map(|b| (b.start, &b.exit)).collect();
+        assert_eq!(
+            exits,
+            vec![
+                (0, &Exit::Next(2)),
+                (
+                    2,
+                    &Exit::Branch {
+                        taken: 14,
+                        not_taken: 5
+                    }
+                ),
+                (5, &Exit::Jump(2)),
+                (
+                    14,
+                    &Exit::Unresolved {
+                        target: Param::PositionMode(19),
+                        not_taken: Some(17)
+                    }
+                ),
+                (17, &Exit::Halt),
+            ]
+        );
+        assert_eq!(cfg.blocks[&5].instructions.len(), 3);
+        assert_eq!(cfg.edges(), vec![(0, 2), (2, 14), (2, 5), (5, 2), (14, 17)]);
+    }
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(
+            control_flow_graph(&countdown()).summary(),
+            concat!(
+                "5 blocks, 5 edges, 1 halts, 1 unresolved jumps\n",
+                "0..2: 1 instructions, then 2\n",
+                "2..5: 1 instructions, branches to 14 or 5\n",
+                "5..14: 3 instructions, jumps to 2\n",
+                "14..17: 1 instructions, branches to [19] (unresolved) or 17\n",
+                "17..18: 1 instructions, halts\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let cfg = control_flow_graph(&[1105, 0, 5, 106, 0, 5]);
+        assert_eq!(
+            cfg.to_dot(),
+            concat!(
+                "digraph cfg {\n",
+                "    node [shape=box, fontname=\"monospace\"];\n",
+                "    b0 [label=\"0: jnz #0, #5\\l3: jz #0, [5]\\l\"];\n",
+                "    u0 [label=\"?\", shape=diamond];\n",
+                "    b0 -> u0 [label=\"[5]\", style=dashed];\n",
+                "}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_invalid_code() {
+        let cfg = control_flow_graph(&[1101, 1, 1, 5, 42]);
+        assert_eq!(cfg.blocks[&0].exit, Exit::Invalid(4));
+    }
+}
@@ -0,0 +1,563 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::prelude::*;
+use std::io::{self, BufRead};
+
+use super::disassembler::Line;
+use super::error::IntcodeError;
+use super::interpreter::{Interpreter, Status};
+use super::parser::Op;
+
+/// How a memory access touched a watched address.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Which accesses to an address a watchpoint reacts to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Watch {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Watch {
+    fn matches(self, access: Access) -> bool {
+        match self {
+            Watch::Read => access == Access::Read,
+            Watch::Write => access == Access::Write,
+            Watch::ReadWrite => true,
+        }
+    }
+}
+
+/// Why the debugger handed control back to the user.
+#[derive(Debug, PartialEq)]
+pub enum Stop {
+    Step,
+    Breakpoint(usize),
+    /// Raised by the instruction at `ip` after it executed. Reads report the
+    /// same value as `old` and `new`.
+    Watchpoint {
+        ip: usize,
+        address: usize,
+        access: Access,
+        old: isize,
+        new: isize,
+    },
+    NeedsInput,
+    Halted,
+}
+
+/// Drives an [`Interpreter`] one instruction at a time, stopping on
+/// breakpoints and on reads or writes of watched addresses.
+pub struct Debugger {
+    interpreter: Interpreter,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeMap<usize, Watch>,
+    outputs: Vec<isize>,
+}
+
+impl Debugger {
+    pub fn new(interpreter: Interpreter) -> Debugger {
+        Debugger {
+            interpreter,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            outputs: vec![],
+        }
+    }
+
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+
+    pub fn add_breakpoint(&mut self, at: usize) {
+        self.breakpoints.insert(at);
+    }
+
+    pub fn remove_breakpoint(&mut self, at: usize) -> bool {
+        self.breakpoints.remove(&at)
+    }
+
+    pub fn watch(&mut self, at: usize, watch: Watch) {
+        self.watchpoints.insert(at, watch);
+    }
+
+    pub fn unwatch(&mut self, at: usize) -> bool {
+        self.watchpoints.remove(&at).is_some()
+    }
+
+    pub fn push_input(&mut self, val: isize) {
+        self.interpreter.push_input(val);
+    }
+
+    /// Outputs produced since the last call.
+    pub fn take_outputs(&mut self) -> Vec<isize> {
+        self.outputs.split_off(0)
+    }
+
+    /// Executes exactly one instruction.
+    pub fn step(&mut self) -> Result<Stop, IntcodeError> {
+        Ok(self.execute_one()?.unwrap_or(Stop::Step))
+    }
+
+    /// Runs until the instruction after the current one is reached, so a
+    /// jump into a subroutine that eventually comes back runs as one step.
+    pub fn step_over(&mut self) -> Result<Stop, IntcodeError> {
+        let ip = self.interpreter.instruction_pointer();
+        let target = ip + self.interpreter.op_at(ip)?.size();
+
+        loop {
+            if let Some(stop) = self.execute_one()? {
+                return Ok(stop);
+            }
+
+            let ip = self.interpreter.instruction_pointer();
+            if ip == target {
+                return Ok(Stop::Step);
+            }
+            if self.breakpoints.contains(&ip) {
+                return Ok(Stop::Breakpoint(ip));
+            }
+        }
+    }
+
+    /// Runs until a breakpoint, a watchpoint, a halt or a read with no input
+    /// queued. A breakpoint on the current instruction doesn't stop it.
+    pub fn cont(&mut self) -> Result<Stop, IntcodeError> {
+        loop {
+            if let Some(stop) = self.execute_one()? {
+                return Ok(stop);
+            }
+
+            let ip = self.interpreter.instruction_pointer();
+            if self.breakpoints.contains(&ip) {
+                return Ok(Stop::Breakpoint(ip));
+            }
+        }
+    }
+
+    fn execute_one(&mut self) -> Result<Option<Stop>, IntcodeError> {
+        let ip = self.interpreter.instruction_pointer();
+        let op = self.interpreter.op_at(ip)?;
+        let (reads, writes) = self.accesses(&op);
+        let read_values: Vec<isize> = reads.iter().map(|&at| self.interpreter.read(at)).collect();
+        let old_values: Vec<isize> = writes.iter().map(|&at| self.interpreter.read(at)).collect();
+
+        match self.interpreter.step()? {
+            Some(Status::NeedsInput) => return Ok(Some(Stop::NeedsInput)),
+            Some(Status::Halted) => return Ok(Some(Stop::Halted)),
+            Some(Status::Output(val)) => self.outputs.push(val),
+            None => (),
+        }
+
+        for (address, old) in writes.into_iter().zip(old_values) {
+            if self.is_watched(address, Access::Write) {
+                return Ok(Some(Stop::Watchpoint {
+                    ip,
+                    address,
+                    access: Access::Write,
+                    old,
+                    new: self.interpreter.read(address),
+                }));
+            }
+        }
+
+        for (address, val) in reads.into_iter().zip(read_values) {
+            if self.is_watched(address, Access::Read) {
+                return Ok(Some(Stop::Watchpoint {
+                    ip,
+                    address,
+                    access: Access::Read,
+                    old: val,
+                    new: val,
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn is_watched(&self, address: usize, access: Access) -> bool {
+        match self.watchpoints.get(&address) {
+            Some(watch) => watch.matches(access),
+            None => false,
+        }
+    }
+
+    // the addresses the instruction will read from and write to
+    fn accesses(&self, op: &Op) -> (Vec<usize>, Vec<usize>) {
+        let reads = self
+            .interpreter
+            .read_params(op)
+            .into_iter()
+            .filter_map(|param| self.interpreter.address_of(param))
+            .collect();
+        let writes = op
+            .written_param()
+            .and_then(|i| self.interpreter.address_of(op.params()[i]))
+            .into_iter()
+            .collect();
+
+        (reads, writes)
+    }
+
+    /// Reads commands from `input` until it runs out or the user quits,
+    /// writing everything the session prints to `out`.
+    pub fn repl(&mut self, input: &mut dyn BufRead, out: &mut dyn Write) -> io::Result<()> {
+        self.print_location(out)?;
+
+        loop {
+            write!(out, "(debug) ")?;
+            out.flush()?;
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let result = match words.as_slice() {
+                [] => continue,
+                ["q"] | ["quit"] => return Ok(()),
+                ["h"] | ["help"] => write!(out, "{}", HELP),
+                ["s"] | ["step"] => {
+                    let stop = self.step();
+                    self.report(stop, out)
+                }
+                ["n"] | ["next"] => {
+                    let stop = self.step_over();
+                    self.report(stop, out)
+                }
+                ["c"] | ["continue"] => {
+                    let stop = self.cont();
+                    self.report(stop, out)
+                }
+                ["b"] | ["break"] => {
+                    for at in &self.breakpoints {
+                        writeln!(out, "breakpoint at {}", at)?;
+                    }
+                    Ok(())
+                }
+                ["b", at] | ["break", at] => match at.parse() {
+                    Ok(at) => {
+                        self.add_breakpoint(at);
+                        writeln!(out, "breakpoint at {}", at)
+                    }
+                    Err(_) => writeln!(out, "invalid address {}", at),
+                },
+                ["d", at] | ["delete", at] => match at.parse() {
+                    Ok(at) if self.remove_breakpoint(at) => {
+                        writeln!(out, "removed breakpoint at {}", at)
+                    }
+                    _ => writeln!(out, "no breakpoint at {}", at),
+                },
+                ["w", at] | ["watch", at] => self.command_watch(at, "w", out),
+                ["w", at, kind] | ["watch", at, kind] => self.command_watch(at, kind, out),
+                ["u", at] | ["unwatch", at] => match at.parse() {
+                    Ok(at) if self.unwatch(at) => writeln!(out, "removed watchpoint on {}", at),
+                    _ => writeln!(out, "no watchpoint on {}", at),
+                },
+                ["x", at] => self.command_examine(at, "1", out),
+                ["x", at, count] => self.command_examine(at, count, out),
+                ["set", at, val] => match (at.parse(), val.parse()) {
+                    (Ok(at), Ok(val)) => match self.interpreter.write(at, val) {
+                        Ok(()) => writeln!(out, "{:>6}: {}", at, val),
+                        Err(err) => writeln!(out, "{}", err),
+                    },
+                    _ => writeln!(out, "usage: set <address> <value>"),
+                },
+                ["i", val] | ["input", val] => match val.parse() {
+                    Ok(val) => {
+                        self.push_input(val);
+                        Ok(())
+                    }
+                    Err(_) => writeln!(out, "invalid input {}", val),
+                },
+                ["r"] | ["regs"] => writeln!(
+                    out,
+                    "ip {}  rb {}",
+                    self.interpreter.instruction_pointer(),
+                    self.interpreter.relative_base()
+                ),
+                ["l"] | ["list"] => self.list(self.interpreter.instruction_pointer(), 10, out),
+                ["l", at] | ["list", at] => match at.parse() {
+                    Ok(at) => self.list(at, 10, out),
+                    Err(_) => writeln!(out, "invalid address {}", at),
+                },
+                _ => writeln!(out, "unknown command, try `help`"),
+            };
+
+            result?;
+        }
+    }
+
+    fn command_watch(&mut self, at: &str, kind: &str, out: &mut dyn Write) -> io::Result<()> {
+        let watch = match kind {
+            "r" => Watch::Read,
+            "w" => Watch::Write,
+            "rw" => Watch::ReadWrite,
+            _ => return writeln!(out, "watch kind must be r, w or rw"),
+        };
+
+        match at.parse() {
+            Ok(at) => {
+                self.watch(at, watch);
+                writeln!(out, "watching {} ({})", at, kind)
+            }
+            Err(_) => writeln!(out, "invalid address {}", at),
+        }
+    }
+
+    fn command_examine(&self, at: &str, count: &str, out: &mut dyn Write) -> io::Result<()> {
+        let (at, count): (usize, usize) = match (at.parse(), count.parse()) {
+            (Ok(at), Ok(count)) => (at, count),
+            _ => return writeln!(out, "usage: x <address> [count]"),
+        };
+        if count == 0 {
+            return Ok(());
+        }
+        let last = match at.checked_add(count - 1) {
+            Some(last) => last,
+            None => return writeln!(out, "{} words from {} is past the last address", count, at),
+        };
+
+        for row in (at..=last).step_by(8) {
+            let words: Vec<String> = (row..=row.saturating_add(7).min(last))
+                .map(|at| self.interpreter.read(at).to_string())
+                .collect();
+            writeln!(out, "{:>6}: {}", row, words.join(" "))?;
+        }
+
+        Ok(())
+    }
+
+    fn list(&self, mut at: usize, count: usize, out: &mut dyn Write) -> io::Result<()> {
+        for _ in 0..count {
+            let line = match self.interpreter.op_at(at) {
+                Ok(op) => Line::Instruction(at, op),
+                Err(_) => Line::Data(at, self.interpreter.read(at)),
+            };
+            let marker = if at == self.interpreter.instruction_pointer() {
+                ">"
+            } else {
+                " "
+            };
+            writeln!(out, "{}{}", marker, line)?;
+
+            let size = match line {
+                Line::Instruction(_, op) => op.size(),
+                Line::Data(..) => 1,
+            };
+            at = match at.checked_add(size) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+
+        Ok(())
+    }
+
+    fn report(&mut self, stop: Result<Stop, IntcodeError>, out: &mut dyn Write) -> io::Result<()> {
+        for val in self.take_outputs() {
+            writeln!(out, "output: {}", val)?;
+        }
+
+        match stop {
+            Ok(Stop::Step) => (),
+            Ok(Stop::Breakpoint(at)) => writeln!(out, "breakpoint at {}", at)?,
+            Ok(Stop::Watchpoint {
+                ip,
+                address,
+                access: Access::Read,
+                new,
+                ..
+            }) => writeln!(out, "instruction {} read {} from {}", ip, new, address)?,
+            Ok(Stop::Watchpoint {
+                ip,
+                address,
+                access: Access::Write,
+                old,
+                new,
+            }) => writeln!(
+                out,
+                "instruction {} wrote {} to {} (was {})",
+                ip, new, address, old
+            )?,
+            Ok(Stop::NeedsInput) => {
+                writeln!(out, "waiting for input, queue some with `i <value>`")?
+            }
+            Ok(Stop::Halted) => writeln!(out, "program halted")?,
+            Err(err) => writeln!(out, "{}", err)?,
+        }
+
+        self.print_location(out)
+    }
+
+    fn print_location(&self, out: &mut dyn Write) -> io::Result<()> {
+        let ip = self.interpreter.instruction_pointer();
+        match self.interpreter.op_at(ip) {
+            Ok(op) => writeln!(out, "{}", Line::Instruction(ip, op)),
+            Err(err) => writeln!(out, "{}", err),
+        }
+    }
+}
+
+const HELP: &str = "\
+s, step              execute one instruction
+n, next              step over the current instruction
+c, continue          run until a breakpoint, watchpoint or halt
+b, break [addr]      set a breakpoint, or list them
+d, delete <addr>     remove a breakpoint
+w, watch <addr> [r|w|rw]
+                     stop when the address is read and/or written
+u, unwatch <addr>    remove a watchpoint
+x <addr> [count]     examine memory
+set <addr> <value>   patch memory
+i, input <value>     queue an input value
+r, regs              show the instruction pointer and relative base
+l, list [addr]       disassemble from the address, or from ip
+q, quit              leave the debugger
+";
+
+#[cfg(test)]
+mod tests {
+    use super::super::DOUBLER;
+    use super::*;
+    use std::io::Cursor;
+
+    fn debugger() -> Debugger {
+        Debugger::new(Interpreter::from_bytecode(&DOUBLER))
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut debugger = debugger();
+        debugger.add_breakpoint(9);
+        debugger.push_input(4);
+
+        assert_eq!(debugger.cont(), Ok(Stop::Breakpoint(9)));
+        assert_eq!(debugger.step(), Ok(Stop::Step));
+        assert_eq!(debugger.take_outputs(), vec![8]);
+        assert_eq!(debugger.cont(), Ok(Stop::NeedsInput));
+
+        debugger.push_input(0);
+        assert!(debugger.remove_breakpoint(9));
+        assert_eq!(debugger.cont(), Ok(Stop::Halted));
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let mut debugger = debugger();
+        debugger.watch(15, Watch::Write);
+        debugger.push_input(4);
+
+        let input_write = Stop::Watchpoint {
+            ip: 0,
+            address: 15,
+            access: Access::Write,
+            old: 0,
+            new: 4,
+        };
+        assert_eq!(debugger.cont(), Ok(input_write));
+
+        let double_write = Stop::Watchpoint {
+            ip: 5,
+            address: 15,
+            access: Access::Write,
+            old: 4,
+            new: 8,
+        };
+        assert_eq!(debugger.cont(), Ok(double_write));
+
+        debugger.watch(15, Watch::Read);
+        let read = Stop::Watchpoint {
+            ip: 9,
+            address: 15,
+            access: Access::Read,
+            old: 8,
+            new: 8,
+        };
+        assert_eq!(debugger.cont(), Ok(read));
+    }
+
+    #[test]
+    fn test_watchpoint_on_untaken_jump_target() {
+        // jz [7], [8] with [7] holding 1, then a read of [8]
+        let src = [6, 7, 8, 4, 8, 99, 0, 1, 0];
+        let mut debugger = Debugger::new(Interpreter::from_bytecode(&src));
+        debugger.watch(8, Watch::Read);
+
+        let read = Stop::Watchpoint {
+            ip: 3,
+            address: 8,
+            access: Access::Read,
+            old: 0,
+            new: 0,
+        };
+        assert_eq!(debugger.cont(), Ok(read));
+    }
+
+    #[test]
+    fn test_step_over() {
+        // jumps to a subroutine at 7 that stores a 7 and jumps back to 3
+        let src = vec![1105, 1, 7, 104, 1, 99, 0, 1101, 3, 4, 6, 1105, 1, 3];
+        let mut debugger = Debugger::new(Interpreter::from_bytecode(&src));
+
+        assert_eq!(debugger.step_over(), Ok(Stop::Step));
+        assert_eq!(debugger.interpreter().instruction_pointer(), 3);
+        assert_eq!(debugger.interpreter().read(6), 7);
+    }
+
+    #[test]
+    fn test_huge_addresses() {
+        let mut debugger = debugger();
+        let mut input = Cursor::new(
+            "x 18446744073709551615 5
+x 18446744073709551615
+l 18446744073709551615
+q
+",
+        );
+        let mut out = vec![];
+        debugger.repl(&mut input, &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "     0: in [15]\n",
+                "(debug) 5 words from 18446744073709551615 is past the last address\n",
+                "(debug) 18446744073709551615: 0\n",
+                "(debug)  18446744073709551615: data 0\n",
+                "(debug) ",
+            )
+        );
+    }
+
+    #[test]
+    fn test_repl() {
+        let mut debugger = debugger();
+        let mut input = Cursor::new("b 9\ni 21\nc\nx 14 2\nset 15 50\nr\nn\nq\n");
+        let mut out = vec![];
+        debugger.repl(&mut input, &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "     0: in [15]\n",
+                "(debug) breakpoint at 9\n",
+                "(debug) ",
+                "(debug) breakpoint at 9\n",
+                "     9: out [15]\n",
+                "(debug)     14: 99 42\n",
+                "(debug)     15: 50\n",
+                "(debug) ip 9  rb 0\n",
+                "(debug) output: 50\n",
+                "    11: jnz #1, #0\n",
+                "(debug) ",
+            )
+        );
+    }
+}
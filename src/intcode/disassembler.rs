@@ -0,0 +1,97 @@
+use std::fmt;
+use std::sync::Arc;
+
+use super::memory::{MemoryManager, ReadOnlyMemoryManager};
+use super::parser::{Op, Parser};
+
+/// One line of a disassembly listing: either a decoded instruction or a word
+/// that couldn't be decoded as one.
+#[derive(Debug, PartialEq)]
+pub enum Line {
+    Instruction(usize, Op),
+    Data(usize, isize),
+}
+
+impl Line {
+    pub fn address(&self) -> usize {
+        match self {
+            Line::Instruction(at, _) | Line::Data(at, _) => *at,
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Line::Instruction(at, op) => write!(f, "{:>6}: {}", at, op),
+            Line::Data(at, word) => write!(f, "{:>6}: data {}", at, word),
+        }
+    }
+}
+
+/// Decodes `src` front to back. Words that don't start a valid instruction,
+/// or start one that runs past the end of the program, become data.
+pub fn disassemble(src: &[isize]) -> Vec<Line> {
+    let parser = Parser::new(Arc::new(MemoryManager::new(src)) as Arc<dyn ReadOnlyMemoryManager>);
+    let mut lines = vec![];
+    let mut at = 0;
+
+    while at < src.len() {
+        match parser.parse_op(at) {
+            Ok(op) if at + op.size() <= src.len() => {
+                let len = op.size();
+                lines.push(Line::Instruction(at, op));
+                at += len;
+            }
+            _ => {
+                lines.push(Line::Data(at, src[at]));
+                at += 1;
+            }
+        }
+    }
+
+    lines
+}
+
+/// The annotated listing for `src`, one line per instruction or data word.
+pub fn listing(src: &[isize]) -> String {
+    disassemble(src)
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        let src = vec![1002, 4, 3, 4, 33, 109, 19, 204, -34, 99, 5, 3];
+        assert_eq!(
+            listing(&src),
+            concat!(
+                "     0: mul [4], #3, [4]\n",
+                "     4: data 33\n",
+                "     5: arb #19\n",
+                "     7: out rb-34\n",
+                "     9: hlt\n",
+                "    10: data 5\n",
+                "    11: data 3\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_disassemble_invalid_modes() {
+        assert_eq!(
+            disassemble(&[301, 4, -1, 99]),
+            vec![
+                Line::Data(0, 301),
+                Line::Data(1, 4),
+                Line::Data(2, -1),
+                Line::Instruction(3, Op::Halt),
+            ]
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::error::Error;
+use std::fmt;
+
+/// A fault raised while decoding or executing the instruction at
+/// `instruction_pointer`, whose raw word was `opcode`.
+#[derive(Debug, PartialEq)]
+pub struct IntcodeError {
+    pub instruction_pointer: usize,
+    pub opcode: isize,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ErrorKind {
+    UnknownOpcode,
+    /// `param` is the 1-based position of the offending parameter.
+    InvalidParameterMode {
+        param: usize,
+        mode: usize,
+    },
+    InvalidAddress(isize),
+    /// A sum, product or relative address doesn't fit in a word.
+    ArithmeticOverflow,
+    MemoryLimitExceeded {
+        address: usize,
+        limit: usize,
+    },
+    InputExhausted,
+    BudgetExhausted,
+    TimeLimitExceeded,
+    InfiniteLoop,
+    /// A write over a word that was already executed as code, trapped by
+    /// the code monitor.
+    CodeOverwritten {
+        address: usize,
+        old: isize,
+        new: isize,
+    },
+}
+
+/// A word of comma-separated bytecode that isn't an integer.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub position: usize,
+    pub token: String,
+}
+
+impl IntcodeError {
+    pub fn new(instruction_pointer: usize, opcode: isize, kind: ErrorKind) -> IntcodeError {
+        IntcodeError {
+            instruction_pointer,
+            opcode,
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at instruction {} (opcode {})",
+            self.kind, self.instruction_pointer, self.opcode
+        )
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::UnknownOpcode => write!(f, "unknown opcode"),
+            ErrorKind::InvalidParameterMode { param, mode } => {
+                write!(f, "invalid mode {} for parameter {}", mode, param)
+            }
+            ErrorKind::InvalidAddress(at) => write!(f, "invalid memory address {}", at),
+            ErrorKind::ArithmeticOverflow => write!(f, "arithmetic overflow"),
+            ErrorKind::MemoryLimitExceeded { address, limit } => write!(
+                f,
+                "address {} is past the memory limit of {} words",
+                address, limit
+            ),
+            ErrorKind::InputExhausted => write!(f, "no input available"),
+            ErrorKind::BudgetExhausted => write!(f, "instruction budget exhausted"),
+            ErrorKind::TimeLimitExceeded => write!(f, "time limit exceeded"),
+            ErrorKind::InfiniteLoop => write!(f, "program is stuck in an infinite loop"),
+            ErrorKind::CodeOverwritten { address, old, new } => {
+                write!(f, "write over code at {} ({} -> {})", address, old, new)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{}] at word {} is not a valid value",
+            self.token, self.position
+        )
+    }
+}
+
+impl Error for IntcodeError {}
+
+impl Error for ParseError {}
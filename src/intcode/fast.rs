@@ -0,0 +1,440 @@
+use std::collections::VecDeque;
+
+use super::error::{ErrorKind, IntcodeError};
+use super::interpreter::Status;
+use super::io::{InputSource, OutputSink};
+use super::memory::DEFAULT_MEMORY_LIMIT;
+
+// the longest instruction is an opcode word and three parameters
+const MAX_INSTRUCTION_SIZE: usize = 4;
+
+#[derive(Clone, Copy)]
+enum Code {
+    Sum,
+    Multiply,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustRelativeBase,
+    Halt,
+}
+
+#[derive(Clone, Copy)]
+enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+// an instruction with its modes and raw parameter words pulled out of memory
+#[derive(Clone, Copy)]
+struct Decoded {
+    code: Code,
+    modes: [Mode; 3],
+    params: [isize; 3],
+}
+
+/// An interpreter built for speed rather than introspection.
+///
+/// It owns its memory outright and keeps every instruction it decodes, so
+/// loops only pay for decoding once. Writing to memory drops the decoded
+/// instructions the written word belongs to, which keeps self-modifying
+/// programs correct. It runs programs exactly like
+/// [`Interpreter`](super::Interpreter) does, errors included, but has no
+/// tracing, snapshots or time limits.
+pub struct FastInterpreter {
+    memory: Vec<isize>,
+    // decoded instruction starting at each address, if any
+    cache: Vec<Option<Decoded>>,
+    limit: usize,
+    instruction_pointer: usize,
+    relative_base: isize,
+    inputs: VecDeque<isize>,
+    executed: u64,
+    budget: Option<u64>,
+}
+
+impl FastInterpreter {
+    pub fn from_bytecode(src: &[isize]) -> FastInterpreter {
+        FastInterpreter::with_memory_limit(src, DEFAULT_MEMORY_LIMIT)
+    }
+
+    pub fn with_memory_limit(src: &[isize], limit: usize) -> FastInterpreter {
+        FastInterpreter {
+            memory: src.to_vec(),
+            cache: vec![None; src.len()],
+            limit: limit.max(src.len()),
+            instruction_pointer: 0,
+            relative_base: 0,
+            inputs: VecDeque::new(),
+            executed: 0,
+            budget: None,
+        }
+    }
+
+    pub fn execute_with(
+        &mut self,
+        input: &mut dyn InputSource,
+        output: &mut dyn OutputSink,
+    ) -> Result<(), IntcodeError> {
+        loop {
+            match self.run()? {
+                Status::NeedsInput => match input.read_input() {
+                    Some(val) => self.push_input(val),
+                    None => return Err(self.fault(ErrorKind::InputExhausted)),
+                },
+                Status::Output(val) => output.write_output(val),
+                Status::Halted => return Ok(()),
+            }
+        }
+    }
+
+    pub fn push_input(&mut self, val: isize) {
+        self.inputs.push_back(val);
+    }
+
+    /// Same as [`Interpreter::run`](super::Interpreter::run).
+    pub fn run(&mut self) -> Result<Status, IntcodeError> {
+        loop {
+            match self.step() {
+                Ok(None) => (),
+                Ok(Some(status)) => return Ok(status),
+                Err(kind) => return Err(self.fault(kind)),
+            }
+        }
+    }
+
+    /// Same as [`Interpreter::set_instruction_budget`](super::Interpreter::set_instruction_budget).
+    pub fn set_instruction_budget(&mut self, budget: Option<u64>) {
+        self.budget = budget;
+    }
+
+    pub fn instructions_executed(&self) -> u64 {
+        self.executed
+    }
+
+    pub fn instruction_pointer(&self) -> usize {
+        self.instruction_pointer
+    }
+
+    pub fn relative_base(&self) -> isize {
+        self.relative_base
+    }
+
+    pub fn read(&self, at: usize) -> isize {
+        self.memory.get(at).copied().unwrap_or(0)
+    }
+
+    pub fn write(&mut self, at: usize, val: isize) -> Result<(), ErrorKind> {
+        self.store(at, val)
+    }
+
+    pub fn dump(&self) -> Vec<isize> {
+        self.memory.clone()
+    }
+
+    fn fault(&self, kind: ErrorKind) -> IntcodeError {
+        IntcodeError::new(
+            self.instruction_pointer,
+            self.read(self.instruction_pointer),
+            kind,
+        )
+    }
+
+    #[inline]
+    fn step(&mut self) -> Result<Option<Status>, ErrorKind> {
+        let ip = self.instruction_pointer;
+        let op = match self.cache.get(ip) {
+            Some(Some(op)) => *op,
+            _ => self.decode(ip)?,
+        };
+
+        if let Code::Halt = op.code {
+            return Ok(Some(Status::Halted));
+        }
+        let waits = matches!(op.code, Code::Input) && self.inputs.is_empty();
+        if !waits && self.budget == Some(0) {
+            return Err(ErrorKind::BudgetExhausted);
+        }
+
+        let status = match op.code {
+            Code::Sum => {
+                let val = self
+                    .arg(&op, 0)?
+                    .checked_add(self.arg(&op, 1)?)
+                    .ok_or(ErrorKind::ArithmeticOverflow)?;
+                self.store(self.address(&op, 2)?, val)?;
+                self.instruction_pointer += 4;
+                None
+            }
+            Code::Multiply => {
+                let val = self
+                    .arg(&op, 0)?
+                    .checked_mul(self.arg(&op, 1)?)
+                    .ok_or(ErrorKind::ArithmeticOverflow)?;
+                self.store(self.address(&op, 2)?, val)?;
+                self.instruction_pointer += 4;
+                None
+            }
+            Code::Input => match self.inputs.pop_front() {
+                Some(val) => {
+                    self.store(self.address(&op, 0)?, val)?;
+                    self.instruction_pointer += 2;
+                    None
+                }
+                None => return Ok(Some(Status::NeedsInput)),
+            },
+            Code::Output => {
+                let val = self.arg(&op, 0)?;
+                self.instruction_pointer += 2;
+                Some(Status::Output(val))
+            }
+            Code::JumpIfTrue => {
+                if self.arg(&op, 0)? != 0 {
+                    self.jump(&op)?;
+                } else {
+                    self.instruction_pointer += 3;
+                }
+                None
+            }
+            Code::JumpIfFalse => {
+                if self.arg(&op, 0)? == 0 {
+                    self.jump(&op)?;
+                } else {
+                    self.instruction_pointer += 3;
+                }
+                None
+            }
+            Code::LessThan => {
+                let val = (self.arg(&op, 0)? < self.arg(&op, 1)?) as isize;
+                self.store(self.address(&op, 2)?, val)?;
+                self.instruction_pointer += 4;
+                None
+            }
+            Code::Equals => {
+                let val = (self.arg(&op, 0)? == self.arg(&op, 1)?) as isize;
+                self.store(self.address(&op, 2)?, val)?;
+                self.instruction_pointer += 4;
+                None
+            }
+            Code::AdjustRelativeBase => {
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(self.arg(&op, 0)?)
+                    .ok_or(ErrorKind::ArithmeticOverflow)?;
+                self.instruction_pointer += 2;
+                None
+            }
+            Code::Halt => unreachable!(),
+        };
+
+        self.executed += 1;
+        if let Some(budget) = self.budget.as_mut() {
+            *budget -= 1;
+        }
+
+        Ok(status)
+    }
+
+    // decodes the instruction at `at` the way `Parser::parse_op` does and
+    // remembers it if it lies within memory
+    fn decode(&mut self, at: usize) -> Result<Decoded, ErrorKind> {
+        let word = self.read(at);
+        let modes = (word / 100) as usize;
+
+        // (code, parameter count, index of the written parameter)
+        let (code, count, written) = match word % 100 {
+            1 => (Code::Sum, 3, Some(2)),
+            2 => (Code::Multiply, 3, Some(2)),
+            3 => (Code::Input, 1, Some(0)),
+            4 => (Code::Output, 1, None),
+            5 => (Code::JumpIfTrue, 2, None),
+            6 => (Code::JumpIfFalse, 2, None),
+            7 => (Code::LessThan, 3, Some(2)),
+            8 => (Code::Equals, 3, Some(2)),
+            9 => (Code::AdjustRelativeBase, 1, None),
+            99 => (Code::Halt, 0, None),
+            _ => return Err(ErrorKind::UnknownOpcode),
+        };
+
+        let mut op = Decoded {
+            code,
+            modes: [Mode::Position; 3],
+            params: [0; 3],
+        };
+        for i in 0..count {
+            let param = self.read(at + i + 1);
+            op.params[i] = param;
+            op.modes[i] = match modes / 10usize.pow(i as u32) % 10 {
+                0 if param < 0 => return Err(ErrorKind::InvalidAddress(param)),
+                0 => Mode::Position,
+                1 if written != Some(i) => Mode::Immediate,
+                2 => Mode::Relative,
+                mode => return Err(ErrorKind::InvalidParameterMode { param: i + 1, mode }),
+            };
+        }
+
+        if at < self.cache.len() {
+            self.cache[at] = Some(op);
+        }
+
+        Ok(op)
+    }
+
+    #[inline]
+    fn arg(&self, op: &Decoded, i: usize) -> Result<isize, ErrorKind> {
+        match op.modes[i] {
+            Mode::Immediate => Ok(op.params[i]),
+            _ => Ok(self.read(self.address(op, i)?)),
+        }
+    }
+
+    #[inline]
+    fn address(&self, op: &Decoded, i: usize) -> Result<usize, ErrorKind> {
+        match op.modes[i] {
+            Mode::Position => Ok(op.params[i] as usize),
+            Mode::Relative => match self.relative_base.checked_add(op.params[i]) {
+                Some(at) if at < 0 => Err(ErrorKind::InvalidAddress(at)),
+                Some(at) => Ok(at as usize),
+                None => Err(ErrorKind::ArithmeticOverflow),
+            },
+            // decoding never lets an immediate parameter be written to
+            Mode::Immediate => Err(ErrorKind::InvalidAddress(op.params[i])),
+        }
+    }
+
+    #[inline]
+    fn jump(&mut self, op: &Decoded) -> Result<(), ErrorKind> {
+        match self.arg(op, 1)? {
+            ip if ip < 0 => Err(ErrorKind::InvalidAddress(ip)),
+            ip => {
+                self.instruction_pointer = ip as usize;
+                Ok(())
+            }
+        }
+    }
+
+    #[inline]
+    fn store(&mut self, at: usize, val: isize) -> Result<(), ErrorKind> {
+        if at >= self.memory.len() {
+            if at >= self.limit {
+                return Err(ErrorKind::MemoryLimitExceeded {
+                    address: at,
+                    limit: self.limit,
+                });
+            }
+            self.memory.resize(at + 1, 0);
+            self.cache.resize(at + 1, None);
+        }
+
+        self.memory[at] = val;
+        // forget every instruction the written word may be part of
+        let first = (at + 1).saturating_sub(MAX_INSTRUCTION_SIZE);
+        for cached in &mut self.cache[first..=at] {
+            *cached = None;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::interpreter::Interpreter;
+    use super::super::DOUBLER;
+    use super::*;
+
+    // runs `src` on both engines with the same inputs and compares
+    // everything they did
+    fn assert_same(src: &[isize], inputs: &[isize]) {
+        let mut fast = FastInterpreter::from_bytecode(src);
+        let mut slow = Interpreter::from_bytecode(src);
+        for &val in inputs {
+            fast.push_input(val);
+            slow.push_input(val);
+        }
+
+        loop {
+            let status = fast.run();
+            assert_eq!(status, slow.run());
+            assert_eq!(fast.instruction_pointer(), slow.instruction_pointer());
+            assert_eq!(fast.dump(), slow.snapshot().memory);
+            if !matches!(status, Ok(Status::Output(_))) {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_same_as_interpreter() {
+        let programs: Vec<(Vec<isize>, Vec<isize>)> = vec![
+            (vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50], vec![]),
+            (vec![1002, 4, 3, 4, 33], vec![]),
+            (
+                vec![
+                    3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0,
+                    36, 98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46,
+                    1101, 1000, 1, 20, 4, 20, 1105, 1, 46, 98, 99,
+                ],
+                vec![8],
+            ),
+            (
+                vec![
+                    109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+                ],
+                vec![],
+            ),
+            (DOUBLER.to_vec(), vec![3, 4, 0]),
+            // errors: unknown opcode, immediate write, negative address, memory
+            // limit, overflow
+            (vec![1, 0, 0, 0, 42], vec![]),
+            (vec![11101, 1, 1, 0, 99], vec![]),
+            (vec![1, -1, 0, 0, 99], vec![]),
+            (vec![109, -5, 22201, 0, 0, 0, 99], vec![]),
+            (vec![3, 0, 99], vec![]),
+            (vec![1102, isize::MAX, 2, 0, 99], vec![]),
+            (vec![109, isize::MAX, 204, 1, 99], vec![]),
+        ];
+
+        for (src, inputs) in programs {
+            assert_same(&src, &inputs);
+        }
+    }
+
+    #[test]
+    fn test_self_modifying_code() {
+        // the first pass turns the `mul` at 4 into an `add`, whose result
+        // is then printed
+        let src = vec![1101, 0, 1, 4, 2, 11, 12, 13, 4, 13, 99, 5, 6, 0];
+        let mut program = FastInterpreter::from_bytecode(&src);
+        assert_eq!(program.run(), Ok(Status::Output(11)));
+        assert_same(&src, &[]);
+    }
+
+    #[test]
+    fn test_cache_invalidated_on_write() {
+        // loops over `out #7`, rewriting it to `out #8` after the first pass
+        let src = vec![104, 7, 1101, 0, 8, 1, 1105, 1, 0];
+        let mut program = FastInterpreter::from_bytecode(&src);
+        assert_eq!(program.run(), Ok(Status::Output(7)));
+        assert_eq!(program.run(), Ok(Status::Output(8)));
+    }
+
+    #[test]
+    fn test_budget() {
+        let mut program = FastInterpreter::from_bytecode(&[1105, 1, 0]);
+        program.set_instruction_budget(Some(10));
+        assert_eq!(
+            program.run().map_err(|err| err.kind),
+            Err(ErrorKind::BudgetExhausted)
+        );
+        assert_eq!(program.instructions_executed(), 10);
+
+        let mut program = FastInterpreter::from_bytecode(&[3, 0, 99]);
+        program.set_instruction_budget(Some(0));
+        assert_eq!(program.run(), Ok(Status::NeedsInput));
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use super::disassembler::listing;
+use super::error::IntcodeError;
+use super::fast::FastInterpreter;
+use super::interpreter::Interpreter;
+
+/// Memory limit every engine runs fuzzed programs with, small enough that
+/// wild writes fail quickly instead of allocating.
+pub const FUZZ_MEMORY_LIMIT: usize = 1 << 12;
+
+/// Instructions each fuzzed program may execute before it is cut off.
+pub const FUZZ_BUDGET: u64 = 200;
+
+const MAX_INSTRUCTIONS: usize = 12;
+const MAX_INPUTS: u64 = 3;
+const OPCODES: [isize; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 99];
+
+/// A xorshift64* generator, so fuzzing runs can be repeated from a seed.
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    pub fn new(seed: u64) -> XorShift {
+        // an all-zero state would only ever produce zeros
+        XorShift {
+            state: if seed == 0 {
+                0x9e37_79b9_7f4a_7c15
+            } else {
+                seed
+            },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// A number in `low..=high`.
+    pub fn between(&mut self, low: isize, high: isize) -> isize {
+        low + self.below((high - low + 1) as u64) as isize
+    }
+}
+
+/// A program to run together with its input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Case {
+    pub program: Vec<isize>,
+    pub inputs: Vec<isize>,
+    pub budget: u64,
+}
+
+/// How a run ended.
+#[derive(Debug, PartialEq)]
+pub enum Ending {
+    Halted,
+    Failed(IntcodeError),
+}
+
+/// Everything a run leaves behind that two engines have to agree on.
+#[derive(Debug, PartialEq)]
+pub struct Outcome {
+    /// Final memory, without trailing zeros since engines may grow memory
+    /// differently.
+    pub memory: Vec<isize>,
+    pub outputs: Vec<isize>,
+    pub ending: Ending,
+}
+
+impl Outcome {
+    pub fn new(
+        mut memory: Vec<isize>,
+        outputs: Vec<isize>,
+        result: Result<(), IntcodeError>,
+    ) -> Outcome {
+        while memory.last() == Some(&0) {
+            memory.pop();
+        }
+
+        Outcome {
+            memory,
+            outputs,
+            ending: match result {
+                Ok(()) => Ending::Halted,
+                Err(err) => Ending::Failed(err),
+            },
+        }
+    }
+}
+
+/// Something that runs intcode. Any `FnMut(&Case) -> Outcome` is one.
+///
+/// Engines must honour `case.budget` the way
+/// [`Interpreter::set_instruction_budget`] does, fail with
+/// `ErrorKind::InputExhausted` once `case.inputs` runs out and limit memory
+/// to [`FUZZ_MEMORY_LIMIT`] words.
+pub trait Engine {
+    fn run(&mut self, case: &Case) -> Outcome;
+}
+
+impl<F: FnMut(&Case) -> Outcome> Engine for F {
+    fn run(&mut self, case: &Case) -> Outcome {
+        self(case)
+    }
+}
+
+/// Runs `case` on [`Interpreter`].
+pub fn interpreter(case: &Case) -> Outcome {
+    let mut program = Interpreter::with_memory_limit(&case.program, FUZZ_MEMORY_LIMIT);
+    program.set_instruction_budget(Some(case.budget));
+
+    let mut inputs: VecDeque<isize> = case.inputs.iter().copied().collect();
+    let mut outputs = vec![];
+    let result = program.execute_with(&mut inputs, &mut outputs);
+    Outcome::new(program.snapshot().memory, outputs, result)
+}
+
+/// Runs `case` on [`FastInterpreter`].
+pub fn fast_interpreter(case: &Case) -> Outcome {
+    let mut program = FastInterpreter::with_memory_limit(&case.program, FUZZ_MEMORY_LIMIT);
+    program.set_instruction_budget(Some(case.budget));
+
+    let mut inputs: VecDeque<isize> = case.inputs.iter().copied().collect();
+    let mut outputs = vec![];
+    let result = program.execute_with(&mut inputs, &mut outputs);
+    Outcome::new(program.dump(), outputs, result)
+}
+
+/// Generates a random program made of valid instructions, covering every
+/// opcode and every parameter mode the opcode allows, plus a few inputs.
+///
+/// Parameters mostly point into the program and jumps mostly land on
+/// instructions, so programs tend to loop and rewrite themselves rather
+/// than fail straight away.
+pub fn generate(rng: &mut XorShift) -> Case {
+    // pick the instructions first, their parameters need the final length
+    let count = 1 + rng.below(MAX_INSTRUCTIONS as u64) as usize;
+    let mut instructions = vec![];
+    let mut starts = vec![];
+    let mut len = 0;
+    for _ in 0..count {
+        let opcode = OPCODES[rng.below(OPCODES.len() as u64) as usize];
+        let (reads, writes) = match opcode {
+            1 | 2 | 7 | 8 => (2, 1),
+            3 => (0, 1),
+            4 | 9 => (1, 0),
+            5 | 6 => (2, 0),
+            _ => (0, 0),
+        };
+
+        // written parameters are never in immediate mode
+        let mut modes: Vec<isize> = (0..reads).map(|_| rng.between(0, 2)).collect();
+        for _ in 0..writes {
+            modes.push(2 * rng.between(0, 1));
+        }
+
+        starts.push(len as isize);
+        len += 1 + modes.len();
+        instructions.push((opcode, modes));
+    }
+    let len = len as isize;
+
+    let mut program = vec![];
+    for (opcode, modes) in instructions {
+        let word = modes.iter().rev().fold(0, |word, mode| word * 10 + mode);
+        program.push(word * 100 + opcode);
+
+        for (i, &mode) in modes.iter().enumerate() {
+            let is_target = (opcode == 5 || opcode == 6) && i == 1;
+            program.push(match mode {
+                0 => rng.between(0, len + 4),
+                1 if is_target && rng.below(4) != 0 => {
+                    starts[rng.below(starts.len() as u64) as usize]
+                }
+                1 => rng.between(-5, len + 4),
+                _ => rng.between(-4, len),
+            });
+        }
+    }
+    if rng.below(2) == 0 {
+        program.push(99);
+    }
+
+    let inputs = (0..rng.below(MAX_INPUTS + 1))
+        .map(|_| rng.between(-5, 5))
+        .collect();
+
+    Case {
+        program,
+        inputs,
+        budget: FUZZ_BUDGET,
+    }
+}
+
+/// A case two engines disagree on, or that made either of them panic.
+///
+/// Each side is the engine's outcome, or the message it panicked with.
+#[derive(Debug)]
+pub struct Mismatch {
+    pub case: Case,
+    pub left: Result<Outcome, String>,
+    pub right: Result<Outcome, String>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "program: {:?}", self.case.program)?;
+        writeln!(f, "inputs: {:?}", self.case.inputs)?;
+        write!(f, "{}", listing(&self.case.program))?;
+        for (side, run) in &[("left", &self.left), ("right", &self.right)] {
+            match run {
+                Ok(outcome) => write!(f, "\n{}: {:?}", side, outcome)?,
+                Err(message) => write!(f, "\n{}: panicked: {}", side, message)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn run_guarded(engine: &mut dyn Engine, case: &Case) -> Result<Outcome, String> {
+    panic::catch_unwind(AssertUnwindSafe(|| engine.run(case))).map_err(|payload| {
+        // `panic!` payloads are a `&str` or, when formatted, a `String`
+        match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&str>() {
+                Ok(message) => String::from(*message),
+                Err(_) => String::from("(no message)"),
+            },
+        }
+    })
+}
+
+/// Runs `case` on both engines, returning what they did if they disagree
+/// or if either panics.
+pub fn compare(case: &Case, left: &mut dyn Engine, right: &mut dyn Engine) -> Option<Mismatch> {
+    let left_outcome = run_guarded(left, case);
+    let right_outcome = run_guarded(right, case);
+
+    match (&left_outcome, &right_outcome) {
+        (Ok(l), Ok(r)) if l == r => None,
+        _ => Some(Mismatch {
+            case: case.clone(),
+            left: left_outcome,
+            right: right_outcome,
+        }),
+    }
+}
+
+// smaller variations of a case: fewer inputs, fewer words, then simpler
+// words
+fn candidates(case: &Case) -> Vec<Case> {
+    let mut candidates = vec![];
+    let with = |program: Vec<isize>, inputs: Vec<isize>| Case {
+        program,
+        inputs,
+        budget: case.budget,
+    };
+
+    for i in 0..case.inputs.len() {
+        let mut inputs = case.inputs.clone();
+        inputs.remove(i);
+        candidates.push(with(case.program.clone(), inputs));
+    }
+
+    let len = case.program.len();
+    if len > 1 {
+        candidates.push(with(case.program[..len / 2].to_vec(), case.inputs.clone()));
+    }
+    for i in (0..len).rev() {
+        let mut program = case.program.clone();
+        program.remove(i);
+        candidates.push(with(program, case.inputs.clone()));
+    }
+
+    for i in 0..len {
+        let word = case.program[i];
+        // dropping the parameter modes keeps an opcode an opcode
+        let mut smaller = vec![0, word % 100, word / 2];
+        smaller.dedup();
+        for val in smaller.into_iter().filter(|&val| val != word) {
+            let mut program = case.program.clone();
+            program[i] = val;
+            candidates.push(with(program, case.inputs.clone()));
+        }
+    }
+
+    candidates
+}
+
+/// Shrinks a mismatch by trying ever smaller cases, keeping any that the
+/// engines still disagree on, until no smaller one does.
+pub fn shrink(mismatch: Mismatch, left: &mut dyn Engine, right: &mut dyn Engine) -> Mismatch {
+    let mut smallest = mismatch;
+    'shrinking: loop {
+        for candidate in candidates(&smallest.case) {
+            if let Some(mismatch) = compare(&candidate, left, right) {
+                smallest = mismatch;
+                continue 'shrinking;
+            }
+        }
+
+        return smallest;
+    }
+}
+
+/// Runs `cases` generated programs on both engines and returns the first
+/// one they disagree on or panic on, shrunk.
+///
+/// Panics are caught, but their messages still reach the panic hook as
+/// usual.
+pub fn fuzz(
+    rng: &mut XorShift,
+    cases: usize,
+    left: &mut dyn Engine,
+    right: &mut dyn Engine,
+) -> Option<Mismatch> {
+    for _ in 0..cases {
+        let case = generate(rng);
+        if let Some(mismatch) = compare(&case, left, right) {
+            return Some(shrink(mismatch, left, right));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::disassembler::{disassemble, Line};
+    use super::super::parser::Param;
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_generate_covers_every_op_and_mode() {
+        let mut rng = XorShift::new(7);
+        let mut seen = HashSet::new();
+        for _ in 0..200 {
+            for line in disassemble(&generate(&mut rng).program) {
+                if let Line::Instruction(_, op) = line {
+                    for param in op.params() {
+                        let mode = match param {
+                            Param::PositionMode(_) => 0,
+                            Param::ImmediateMode(_) => 1,
+                            Param::RelativeMode(_) => 2,
+                        };
+                        seen.insert((op.mnemonic(), mode));
+                    }
+                    seen.insert((op.mnemonic(), 9));
+                }
+            }
+        }
+
+        for &op in &["add", "mul", "lt", "eq", "jnz", "jz", "out", "arb"] {
+            for &mode in &[0, 1, 2] {
+                assert!(seen.contains(&(op, mode)), "{} in mode {}", op, mode);
+            }
+        }
+        assert!(seen.contains(&("in", 0)) && seen.contains(&("in", 2)));
+        assert!(seen.contains(&("hlt", 9)));
+    }
+
+    #[test]
+    fn test_same_seed_same_cases() {
+        let (mut a, mut b) = (XorShift::new(42), XorShift::new(42));
+        for _ in 0..10 {
+            assert_eq!(generate(&mut a), generate(&mut b));
+        }
+    }
+
+    #[test]
+    fn test_engines_agree() {
+        let mut rng = XorShift::new(2019);
+        let result = fuzz(&mut rng, 2000, &mut interpreter, &mut fast_interpreter);
+        if let Some(mismatch) = result {
+            panic!("engines disagree:\n{}", mismatch);
+        }
+    }
+
+    #[test]
+    fn test_shrinks_to_minimal_program() {
+        // an engine that adds whenever it should multiply
+        let mut broken = |case: &Case| {
+            let mut program = case.program.clone();
+            for word in program.iter_mut() {
+                if *word % 100 == 2 {
+                    *word -= 1;
+                }
+            }
+            interpreter(&Case {
+                program,
+                ..case.clone()
+            })
+        };
+
+        let mut rng = XorShift::new(1);
+        let mismatch = fuzz(&mut rng, 1000, &mut interpreter, &mut broken).unwrap();
+        // a lone multiplication squaring its own opcode, which the adding
+        // engine has rewritten to 1
+        assert_eq!(mismatch.case.program, vec![2]);
+        assert!(mismatch.case.inputs.is_empty());
+        for candidate in candidates(&mismatch.case) {
+            assert!(compare(&candidate, &mut interpreter, &mut broken).is_none());
+        }
+    }
+
+    #[test]
+    fn test_panics_are_findings() {
+        let mut panicky = |case: &Case| {
+            if case.program.contains(&99) {
+                panic!("halt at {:?}", case.program.iter().position(|&w| w == 99));
+            }
+            interpreter(case)
+        };
+
+        let mut rng = XorShift::new(3);
+        let mismatch = fuzz(&mut rng, 100, &mut interpreter, &mut panicky).unwrap();
+        assert_eq!(mismatch.case.program, vec![99]);
+        assert_eq!(mismatch.right.as_ref().unwrap_err(), "halt at Some(0)");
+        assert!(mismatch
+            .to_string()
+            .ends_with("right: panicked: halt at Some(0)"));
+    }
+}
@@ -0,0 +1,403 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use super::error::ParseError;
+use super::interpreter::Interpreter;
+use super::memory::DEFAULT_MEMORY_LIMIT;
+use super::parser::parse_bytecode_string;
+use super::snapshot::Snapshot;
+
+/// First bytes of every image file.
+pub const IMAGE_MAGIC: [u8; 4] = *b"ICBC";
+
+/// Version written into, and expected from, an image after the magic.
+pub const IMAGE_VERSION: u32 = 1;
+
+// set in the flags byte when a metadata section follows the words
+const HAS_METADATA: u8 = 1;
+
+// a u64 never takes more than this many 7-bit groups
+const MAX_VARINT_BYTES: usize = 10;
+
+/// An intcode program in a compact binary form, optionally with a few
+/// facts about it that comma-separated text has no room for.
+///
+/// The file is laid out as:
+///
+/// ```text
+/// magic      "ICBC"
+/// version    varint
+/// flags      one byte, bit 0 set if metadata follows the words
+/// count      varint, the number of words
+/// words      zigzag varints
+/// metadata   name, entry point, symbol count, then every symbol's name
+///            and address
+/// ```
+///
+/// Varints are unsigned LEB128, so small words take a single byte, and
+/// strings are a varint byte length followed by UTF-8.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub words: Vec<isize>,
+    pub metadata: Option<Metadata>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    pub name: String,
+    /// Address execution starts from.
+    pub entry_point: usize,
+    /// Named addresses, e.g. assembler labels, in the order they were added.
+    pub symbols: Vec<(String, usize)>,
+}
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(io::Error),
+    NotAnImage,
+    UnsupportedVersion(u64),
+    /// The file ends in the middle of something.
+    Truncated,
+    Malformed(String),
+    /// The program isn't an image and isn't valid bytecode text either.
+    Text(ParseError),
+}
+
+/// Whether `bytes` start like an image, as opposed to bytecode text.
+pub fn is_image(bytes: &[u8]) -> bool {
+    bytes.starts_with(&IMAGE_MAGIC)
+}
+
+/// Reads a program that is either an image or comma-separated text. Text
+/// becomes an image without metadata.
+pub fn parse_program(bytes: &[u8]) -> Result<Image, ImageError> {
+    if is_image(bytes) {
+        return Image::from_bytes(bytes);
+    }
+
+    parse_bytecode_string(&String::from_utf8_lossy(bytes))
+        .map(|words| Image::new(&words))
+        .map_err(ImageError::Text)
+}
+
+impl Image {
+    pub fn new(words: &[isize]) -> Image {
+        Image {
+            words: words.to_vec(),
+            metadata: None,
+        }
+    }
+
+    /// An interpreter with the program loaded, about to run from the entry
+    /// point.
+    pub fn interpreter(&self) -> Interpreter {
+        Interpreter::from_snapshot(&Snapshot {
+            instruction_pointer: self.metadata.as_ref().map_or(0, |meta| meta.entry_point),
+            relative_base: 0,
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            inputs: vec![],
+            memory: self.words.clone(),
+        })
+    }
+
+    pub fn write_to(&self, out: &mut dyn Write) -> io::Result<()> {
+        out.write_all(&IMAGE_MAGIC)?;
+        write_varint(out, u64::from(IMAGE_VERSION))?;
+        out.write_all(&[if self.metadata.is_some() {
+            HAS_METADATA
+        } else {
+            0
+        }])?;
+
+        write_varint(out, self.words.len() as u64)?;
+        for &word in &self.words {
+            write_varint(out, zigzag(word))?;
+        }
+
+        if let Some(meta) = self.metadata.as_ref() {
+            write_string(out, &meta.name)?;
+            write_varint(out, meta.entry_point as u64)?;
+            write_varint(out, meta.symbols.len() as u64)?;
+            for (name, at) in &meta.symbols {
+                write_string(out, name)?;
+                write_varint(out, *at as u64)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn read_from(input: &mut dyn Read) -> Result<Image, ImageError> {
+        let mut bytes = vec![];
+        input.read_to_end(&mut bytes)?;
+        Image::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Image, ImageError> {
+        if !is_image(bytes) {
+            return Err(ImageError::NotAnImage);
+        }
+
+        let mut reader = Reader {
+            bytes,
+            at: IMAGE_MAGIC.len(),
+        };
+        let version = reader.varint()?;
+        if version != u64::from(IMAGE_VERSION) {
+            return Err(ImageError::UnsupportedVersion(version));
+        }
+
+        let flags = reader.byte()?;
+        if flags & !HAS_METADATA != 0 {
+            return Err(malformed(&format!("unknown flags {:#04x}", flags)));
+        }
+
+        // every word takes at least a byte, which keeps a corrupt count from
+        // reserving absurd amounts of memory
+        let count = reader.length()?;
+        let mut words = Vec::with_capacity(count);
+        for _ in 0..count {
+            words.push(reader.word()?);
+        }
+
+        let metadata = if flags & HAS_METADATA != 0 {
+            let name = reader.string()?;
+            let entry_point = reader.address()?;
+            let mut symbols = vec![];
+            for _ in 0..reader.length()? {
+                symbols.push((reader.string()?, reader.address()?));
+            }
+
+            Some(Metadata {
+                name,
+                entry_point,
+                symbols,
+            })
+        } else {
+            None
+        };
+
+        if reader.at != bytes.len() {
+            return Err(malformed("trailing bytes after the image"));
+        }
+
+        Ok(Image { words, metadata })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_to(&mut out)?;
+        out.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Image, ImageError> {
+        Image::read_from(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+// maps small negative numbers to small unsigned ones: 0, -1, 1, -2, ...
+fn zigzag(word: isize) -> u64 {
+    let word = word as i64;
+    ((word << 1) ^ (word >> 63)) as u64
+}
+
+fn unzigzag(val: u64) -> i64 {
+    (val >> 1) as i64 ^ -((val & 1) as i64)
+}
+
+fn write_varint(out: &mut dyn Write, mut val: u64) -> io::Result<()> {
+    let mut bytes = vec![];
+    loop {
+        let byte = (val & 0x7f) as u8;
+        val >>= 7;
+        if val == 0 {
+            bytes.push(byte);
+            break;
+        }
+        bytes.push(byte | 0x80);
+    }
+
+    out.write_all(&bytes)
+}
+
+fn write_string(out: &mut dyn Write, text: &str) -> io::Result<()> {
+    write_varint(out, text.len() as u64)?;
+    out.write_all(text.as_bytes())
+}
+
+fn malformed(message: &str) -> ImageError {
+    ImageError::Malformed(String::from(message))
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, ImageError> {
+        let byte = *self.bytes.get(self.at).ok_or(ImageError::Truncated)?;
+        self.at += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u64, ImageError> {
+        let mut val = 0;
+        for i in 0..MAX_VARINT_BYTES {
+            let byte = self.byte()?;
+            val |= u64::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(val);
+            }
+        }
+
+        Err(malformed("varint longer than 10 bytes"))
+    }
+
+    fn word(&mut self) -> Result<isize, ImageError> {
+        let val = unzigzag(self.varint()?);
+        isize::try_from(val).map_err(|_| malformed(&format!("word {} is out of range", val)))
+    }
+
+    fn address(&mut self) -> Result<usize, ImageError> {
+        let val = self.varint()?;
+        usize::try_from(val).map_err(|_| malformed(&format!("address {} is out of range", val)))
+    }
+
+    // a count of things that take at least a byte each
+    fn length(&mut self) -> Result<usize, ImageError> {
+        match self.address()? {
+            len if len > self.bytes.len() - self.at => Err(ImageError::Truncated),
+            len => Ok(len),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ImageError> {
+        let len = self.length()?;
+        let text = &self.bytes[self.at..self.at + len];
+        self.at += len;
+        String::from_utf8(text.to_vec()).map_err(|_| malformed("string is not UTF-8"))
+    }
+}
+
+impl From<io::Error> for ImageError {
+    fn from(err: io::Error) -> ImageError {
+        ImageError::Io(err)
+    }
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageError::Io(err) => write!(f, "{}", err),
+            ImageError::NotAnImage => write!(f, "not an intcode image"),
+            ImageError::UnsupportedVersion(version) => write!(
+                f,
+                "image version {} is not supported, expected {}",
+                version, IMAGE_VERSION
+            ),
+            ImageError::Truncated => write!(f, "image is truncated"),
+            ImageError::Malformed(message) => write!(f, "malformed image: {}", message),
+            ImageError::Text(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ImageError {}
+
+#[cfg(test)]
+mod tests {
+    use super::super::interpreter::Status;
+    use super::*;
+    use std::fs;
+
+    fn to_bytes(image: &Image) -> Vec<u8> {
+        let mut bytes = vec![];
+        image.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_varint_encoding() {
+        let image = Image::new(&[0, -1, 1, 63, -64, 64, 1_000_000, isize::MIN, isize::MAX]);
+        let bytes = to_bytes(&image);
+
+        // magic, version, flags and count, then the words
+        assert_eq!(&bytes[..7], b"ICBC\x01\x00\x09");
+        assert_eq!(&bytes[7..13], &[0x00, 0x01, 0x02, 0x7e, 0x7f, 0x80]);
+        assert_eq!(Image::from_bytes(&bytes).unwrap(), image);
+    }
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let image = Image {
+            words: vec![3, 9, 4, 9, 99, 1005, 9, 0, 99, 0],
+            metadata: Some(Metadata {
+                name: String::from("echo"),
+                entry_point: 2,
+                symbols: vec![(String::from("start"), 2), (String::from("n"), 9)],
+            }),
+        };
+        let restored = Image::from_bytes(&to_bytes(&image)).unwrap();
+        assert_eq!(restored, image);
+
+        // starting at the entry point skips the input
+        let mut program = restored.interpreter();
+        assert_eq!(program.run(), Ok(Status::Output(0)));
+        assert_eq!(program.run(), Ok(Status::Halted));
+    }
+
+    #[test]
+    fn test_smaller_than_text() {
+        let text = fs::read_to_string("data/day_2_intcode.txt").unwrap();
+        let image = Image::new(&parse_bytecode_string(&text).unwrap());
+        assert!(to_bytes(&image).len() < text.len());
+    }
+
+    #[test]
+    fn test_image_errors() {
+        assert!(matches!(
+            Image::from_bytes(b"1,2,3"),
+            Err(ImageError::NotAnImage)
+        ));
+        assert!(matches!(
+            Image::from_bytes(b"ICBC\x02\x00\x00"),
+            Err(ImageError::UnsupportedVersion(2))
+        ));
+        // says three words, has two
+        assert!(matches!(
+            Image::from_bytes(b"ICBC\x01\x00\x03\x02\x04"),
+            Err(ImageError::Truncated)
+        ));
+        assert!(matches!(
+            Image::from_bytes(b"ICBC\x01\x00\x01\x02\x04"),
+            Err(ImageError::Malformed(_))
+        ));
+        assert!(matches!(
+            Image::from_bytes(b"ICBC\x01\x00\x01\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\x01"),
+            Err(ImageError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_program() {
+        let image = Image {
+            words: vec![104, 1, 99],
+            metadata: Some(Metadata::default()),
+        };
+        assert_eq!(parse_program(&to_bytes(&image)).unwrap(), image);
+        assert_eq!(
+            parse_program(b"104,1,99\n").unwrap(),
+            Image::new(&[104, 1, 99])
+        );
+        assert!(matches!(
+            parse_program(b"104,x,99"),
+            Err(ImageError::Text(_))
+        ));
+    }
+}
@@ -1,137 +1,511 @@
-use std::io::prelude::*;
-use std::io::{stdin, stdout};
-use std::rc::Rc;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use super::memory::{MemoryManager, MutableMemoryManager, ReadOnlyMemoryManager};
+use super::error::{ErrorKind, IntcodeError, ParseError};
+use super::io::{InputSource, OutputSink, StdinInput, StdoutOutput};
+use super::memory::{
+    MemoryManager, MutableMemoryManager, ReadOnlyMemoryManager, DEFAULT_MEMORY_LIMIT,
+};
+use super::monitor::{CodeMonitor, CodeWrite, OnCodeWrite};
 use super::parser::{parse_bytecode_string, Op, Param, Parser};
+use super::snapshot::Snapshot;
+use super::trace::{TraceStep, Tracer};
 
 pub struct Interpreter {
-    memory: Rc<dyn MutableMemoryManager>,
+    memory: Arc<dyn MutableMemoryManager>,
     instruction_pointer: usize,
+    relative_base: isize,
     parser: Parser,
+    inputs: VecDeque<isize>,
+    tracer: Option<Box<dyn Tracer>>,
+    executed: u64,
+    budget: Option<u64>,
+    deadline: Option<Instant>,
+    seen_states: Option<HashSet<(usize, isize, Vec<isize>)>>,
+    code_monitor: Option<CodeMonitor>,
+}
+
+// how many instructions run between two looks at the clock
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+/// Why [`Interpreter::run`] handed control back to the caller.
+#[derive(Debug, PartialEq)]
+pub enum Status {
+    NeedsInput,
+    Output(isize),
+    Halted,
 }
 
 impl Interpreter {
-    pub fn from_string(src: &str) -> Interpreter {
-        let init_memory = parse_bytecode_string(src);
-        let memory = Rc::new(MemoryManager::new(&init_memory));
-        let parser = Parser::new(Rc::clone(&memory) as Rc<dyn ReadOnlyMemoryManager>);
+    pub fn from_string(src: &str) -> Result<Interpreter, ParseError> {
+        Ok(Interpreter::from_bytecode(&parse_bytecode_string(src)?))
+    }
+
+    pub fn from_bytecode(src: &[isize]) -> Interpreter {
+        Interpreter::with_memory_limit(src, DEFAULT_MEMORY_LIMIT)
+    }
+
+    pub fn with_memory_limit(src: &[isize], limit: usize) -> Interpreter {
+        let memory = Arc::new(MemoryManager::with_limit(src, limit));
+        let parser = Parser::new(Arc::clone(&memory) as Arc<dyn ReadOnlyMemoryManager>);
 
         Interpreter {
             instruction_pointer: 0,
+            relative_base: 0,
             memory,
             parser,
+            inputs: VecDeque::new(),
+            tracer: None,
+            executed: 0,
+            budget: None,
+            deadline: None,
+            seen_states: None,
+            code_monitor: None,
         }
     }
 
-    pub fn from_bytecode(src: &Vec<isize>) -> Interpreter {
-        let memory = Rc::new(MemoryManager::new(src));
-        let parser = Parser::new(Rc::clone(&memory) as Rc<dyn ReadOnlyMemoryManager>);
+    /// Rebuilds a paused interpreter from a snapshot, with its pending input
+    /// queued. Tracers, limits and loop detection aren't part of a snapshot.
+    pub fn from_snapshot(snapshot: &Snapshot) -> Interpreter {
+        let mut interpreter =
+            Interpreter::with_memory_limit(&snapshot.memory, snapshot.memory_limit);
+        interpreter.instruction_pointer = snapshot.instruction_pointer;
+        interpreter.relative_base = snapshot.relative_base;
+        interpreter.inputs = snapshot.inputs.iter().copied().collect();
+        interpreter
+    }
 
-        Interpreter {
-            instruction_pointer: 0,
-            memory,
-            parser,
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            instruction_pointer: self.instruction_pointer,
+            relative_base: self.relative_base,
+            memory_limit: self.memory.limit(),
+            inputs: self.inputs.iter().copied().collect(),
+            memory: self.memory.dump(),
         }
     }
 
-    pub fn execute(&mut self) {
+    /// Runs the program to completion, reading inputs from stdin and
+    /// printing outputs to stdout.
+    pub fn execute(&mut self) -> Result<(), IntcodeError> {
+        self.execute_with(&mut StdinInput, &mut StdoutOutput)
+    }
+
+    pub fn execute_with(
+        &mut self,
+        input: &mut dyn InputSource,
+        output: &mut dyn OutputSink,
+    ) -> Result<(), IntcodeError> {
         loop {
-            let op = self.parser.parse_op(self.instruction_pointer);
-            match op {
-                Op::Halt => break,
-
-                Op::Sum(a, b, addr) => {
-                    self.memory
-                        .write(addr, self.read_parameter(a) + self.read_parameter(b));
-                    self.instruction_pointer += 4;
-                }
+            match self.run()? {
+                Status::NeedsInput => match input.read_input() {
+                    Some(val) => self.push_input(val),
+                    None => return Err(self.fault(ErrorKind::InputExhausted)),
+                },
+                Status::Output(val) => output.write_output(val),
+                Status::Halted => return Ok(()),
+            }
+        }
+    }
 
-                Op::Multiply(a, b, addr) => {
-                    self.memory
-                        .write(addr, self.read_parameter(a) * self.read_parameter(b));
-                    self.instruction_pointer += 4;
-                }
+    /// Queues a value for the next `Op::Input` instruction.
+    pub fn push_input(&mut self, val: isize) {
+        self.inputs.push_back(val);
+    }
+
+    /// Executes instructions until the program produces output, asks for
+    /// input that hasn't been pushed yet or halts. Calling `run` again picks
+    /// up where the previous call stopped.
+    pub fn run(&mut self) -> Result<Status, IntcodeError> {
+        loop {
+            if let Some(status) = self.step()? {
+                return Ok(status);
+            }
+        }
+    }
 
-                Op::Input(addr) => {
-                    let mut val = String::new();
-                    if stdin().read_line(&mut val).is_ok() {
-                        if let Ok(val) = val.split_whitespace().next().unwrap().parse::<isize>() {
-                            self.memory.write(addr, val)
-                        }
-                    }
+    /// Executes a single instruction, returning the event it caused, if any.
+    ///
+    /// Neither `Op::Halt` nor an `Op::Input` without queued input moves the
+    /// instruction pointer, so stepping again repeats the same status.
+    pub fn step(&mut self) -> Result<Option<Status>, IntcodeError> {
+        let op = self.parser.parse_op(self.instruction_pointer)?;
+        // halting or waiting for input doesn't execute anything, so neither
+        // is stopped by the budget
+        let waits = matches!(op, Op::Input(..)) && self.inputs.is_empty();
+        if op != Op::Halt && !waits && self.budget == Some(0) {
+            return Err(self.fault(ErrorKind::BudgetExhausted));
+        }
 
-                    self.instruction_pointer += 2;
-                }
+        if let Some(monitor) = self.code_monitor.as_mut() {
+            monitor.mark_executed(self.instruction_pointer, op.size());
+        }
+
+        let jumps = match op {
+            Op::JumpIfTrue(..) | Op::JumpIfFalse(..) => true,
+            Op::Input(..) if self.seen_states.is_some() && !self.inputs.is_empty() => {
+                // reading input changes what happens next, so states seen
+                // before it don't prove anything about states after it
+                self.seen_states = Some(HashSet::new());
+                false
+            }
+            _ => false,
+        };
+
+        let status = if self.tracer.is_some() {
+            self.step_traced(op)
+        } else {
+            self.execute_op(op)
+        }
+        .map_err(|kind| self.fault(kind))?;
+
+        if matches!(status, None | Some(Status::Output(_))) {
+            self.count_instruction()?;
+            if jumps && self.seen_states.is_some() {
+                self.check_for_loop()?;
+            }
+        }
+
+        Ok(status)
+    }
+
+    /// Limits how many more instructions may execute before running fails
+    /// with `ErrorKind::BudgetExhausted`, or lifts the limit with `None`.
+    pub fn set_instruction_budget(&mut self, budget: Option<u64>) {
+        self.budget = budget;
+    }
+
+    /// Makes running fail with `ErrorKind::TimeLimitExceeded` once `limit`
+    /// has passed from now, or lifts the limit with `None`. The clock is only
+    /// looked at every so many instructions, so the limit can be overrun
+    /// slightly.
+    pub fn set_time_limit(&mut self, limit: Option<Duration>) {
+        self.deadline = limit.map(|limit| Instant::now() + limit);
+    }
+
+    /// Makes running fail with `ErrorKind::InfiniteLoop` as soon as the
+    /// program gets back to a state (instruction pointer, relative base and
+    /// memory) it was in before without reading input in between. This keeps
+    /// a copy of memory at every jump, so it's only meant for small programs.
+    pub fn set_loop_detection(&mut self, enabled: bool) {
+        self.seen_states = if enabled { Some(HashSet::new()) } else { None };
+    }
+
+    /// Watches for the program writing over words it has executed as code
+    /// from now on, or stops watching with `None`. Only writes made by
+    /// instructions are checked, not ones made through [`write`](Self::write).
+    pub fn set_code_monitor(&mut self, on_write: Option<OnCodeWrite>) {
+        self.code_monitor = on_write.map(CodeMonitor::new);
+    }
+
+    /// Writes over code reported by the code monitor so far.
+    pub fn code_writes(&self) -> &[CodeWrite] {
+        match self.code_monitor.as_ref() {
+            Some(monitor) => monitor.writes(),
+            None => &[],
+        }
+    }
+
+    /// Number of instructions executed so far, halts and waits for input not
+    /// included.
+    pub fn instructions_executed(&self) -> u64 {
+        self.executed
+    }
+
+    fn count_instruction(&mut self) -> Result<(), IntcodeError> {
+        self.executed += 1;
+        if let Some(budget) = self.budget.as_mut() {
+            *budget -= 1;
+        }
+
+        if let Some(deadline) = self.deadline {
+            if self.executed.is_multiple_of(DEADLINE_CHECK_INTERVAL) && Instant::now() >= deadline {
+                return Err(self.fault(ErrorKind::TimeLimitExceeded));
+            }
+        }
+
+        Ok(())
+    }
 
-                Op::Output(addr) => {
-                    if writeln!(stdout(), "{}", self.memory.read(addr)).is_ok() {
-                        //
-                    }
+    // every loop goes through a jump, so it's enough to remember the states
+    // right after them
+    fn check_for_loop(&mut self) -> Result<(), IntcodeError> {
+        let state = (
+            self.instruction_pointer,
+            self.relative_base,
+            self.memory.dump(),
+        );
 
+        let seen = self.seen_states.as_mut().unwrap();
+        if !seen.insert(state) {
+            return Err(self.fault(ErrorKind::InfiniteLoop));
+        }
+
+        Ok(())
+    }
+
+    /// Reports every instruction executed from now on to `tracer`.
+    pub fn set_tracer(&mut self, tracer: Box<dyn Tracer>) {
+        self.tracer = Some(tracer);
+    }
+
+    /// Uninstalls the tracer, handing it back.
+    pub fn take_tracer(&mut self) -> Option<Box<dyn Tracer>> {
+        self.tracer.take()
+    }
+
+    // captures what the instruction is about to read and write, executes it
+    // and reports what happened
+    fn step_traced(&mut self, op: Op) -> Result<Option<Status>, ErrorKind> {
+        let ip = self.instruction_pointer;
+        let written = op.written_param();
+        let mut args = vec![];
+        let mut reads = vec![];
+        // read before executing, which may overwrite the parameters; these
+        // are the reads `execute_op` makes, so they fail the same way
+        for param in self.read_params(&op) {
+            args.push(self.read_parameter(*param)?);
+            reads.extend(self.address_of(param));
+        }
+
+        let status = self.execute_op(op.clone())?;
+        // nothing was executed, the input instruction runs again once resumed
+        if status == Some(Status::NeedsInput) {
+            return Ok(status);
+        }
+
+        // writing instructions leave the relative base alone, so this is
+        // the address that was just written
+        let write = match written {
+            Some(i) => {
+                let at = self.resolve_address(*op.params()[i])?;
+                Some((at, self.memory.read(at)))
+            }
+            None => None,
+        };
+        let step = TraceStep {
+            ip,
+            input: match op {
+                Op::Input(_) => write.map(|(_, val)| val),
+                _ => None,
+            },
+            output: match status {
+                Some(Status::Output(val)) => Some(val),
+                _ => None,
+            },
+            jump: match op {
+                Op::JumpIfTrue(..) if args[0] != 0 => Some(args[1] as usize),
+                Op::JumpIfFalse(..) if args[0] == 0 => Some(args[1] as usize),
+                _ => None,
+            },
+            base: match op {
+                Op::AdjustRelativeBase(_) => Some(self.relative_base),
+                _ => None,
+            },
+            op,
+            args,
+            reads,
+            write,
+        };
+
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.trace(&step);
+        }
+
+        Ok(status)
+    }
+
+    fn execute_op(&mut self, op: Op) -> Result<Option<Status>, ErrorKind> {
+        match op {
+            Op::Halt => return Ok(Some(Status::Halted)),
+
+            Op::Sum(a, b, addr) => {
+                let val = self
+                    .read_parameter(a)?
+                    .checked_add(self.read_parameter(b)?)
+                    .ok_or(ErrorKind::ArithmeticOverflow)?;
+                self.write_parameter(addr, val)?;
+                self.instruction_pointer += 4;
+            }
+
+            Op::Multiply(a, b, addr) => {
+                let val = self
+                    .read_parameter(a)?
+                    .checked_mul(self.read_parameter(b)?)
+                    .ok_or(ErrorKind::ArithmeticOverflow)?;
+                self.write_parameter(addr, val)?;
+                self.instruction_pointer += 4;
+            }
+
+            Op::Input(addr) => match self.inputs.pop_front() {
+                Some(val) => {
+                    self.write_parameter(addr, val)?;
                     self.instruction_pointer += 2;
                 }
+                None => return Ok(Some(Status::NeedsInput)),
+            },
 
-                Op::JumpIfTrue(test, ip) => {
-                    if self.read_parameter(test) != 0 {
-                        self.instruction_pointer = self.read_parameter(ip) as usize;
-                    } else {
-                        self.instruction_pointer += 3;
-                    }
-                }
+            Op::Output(val) => {
+                let val = self.read_parameter(val)?;
+                self.instruction_pointer += 2;
+                return Ok(Some(Status::Output(val)));
+            }
 
-                Op::JumpIfFalse(test, ip) => {
-                    if self.read_parameter(test) == 0 {
-                        self.instruction_pointer = self.read_parameter(ip) as usize;
-                    } else {
-                        self.instruction_pointer += 3;
-                    }
+            Op::JumpIfTrue(test, ip) => {
+                if self.read_parameter(test)? != 0 {
+                    self.instruction_pointer = self.jump_target(ip)?;
+                } else {
+                    self.instruction_pointer += 3;
                 }
+            }
 
-                Op::LessThan(a, b, addr) => {
-                    self.memory.write(
-                        addr,
-                        if self.read_parameter(a) < self.read_parameter(b) {
-                            1
-                        } else {
-                            0
-                        },
-                    );
-                    self.instruction_pointer += 4;
+            Op::JumpIfFalse(test, ip) => {
+                if self.read_parameter(test)? == 0 {
+                    self.instruction_pointer = self.jump_target(ip)?;
+                } else {
+                    self.instruction_pointer += 3;
                 }
+            }
 
-                Op::Equals(a, b, addr) => {
-                    self.memory.write(
-                        addr,
-                        if self.read_parameter(a) == self.read_parameter(b) {
-                            1
-                        } else {
-                            0
-                        },
-                    );
-                    self.instruction_pointer += 4;
-                }
+            Op::LessThan(a, b, addr) => {
+                self.write_parameter(
+                    addr,
+                    if self.read_parameter(a)? < self.read_parameter(b)? {
+                        1
+                    } else {
+                        0
+                    },
+                )?;
+                self.instruction_pointer += 4;
+            }
+
+            Op::Equals(a, b, addr) => {
+                self.write_parameter(
+                    addr,
+                    if self.read_parameter(a)? == self.read_parameter(b)? {
+                        1
+                    } else {
+                        0
+                    },
+                )?;
+                self.instruction_pointer += 4;
+            }
 
-                _ => panic!("Unknown operation"),
+            Op::AdjustRelativeBase(offset) => {
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(self.read_parameter(offset)?)
+                    .ok_or(ErrorKind::ArithmeticOverflow)?;
+                self.instruction_pointer += 2;
             }
         }
+
+        Ok(None)
+    }
+
+    pub fn instruction_pointer(&self) -> usize {
+        self.instruction_pointer
+    }
+
+    pub fn relative_base(&self) -> isize {
+        self.relative_base
+    }
+
+    /// Decodes the instruction at `at` without executing it.
+    pub fn op_at(&self, at: usize) -> Result<Op, IntcodeError> {
+        self.parser.parse_op(at)
     }
 
     pub fn read(&self, at: usize) -> isize {
         self.memory.read(at)
     }
 
-    fn read_parameter(&self, param: Param) -> isize {
+    pub fn write(&mut self, at: usize, val: isize) -> Result<(), ErrorKind> {
+        self.memory.write(at, val)
+    }
+
+    /// The parameters executing `op` right now would read, in order: all but
+    /// the one it writes to, except that a jump only reads its target when
+    /// it's taken.
+    pub fn read_params<'a>(&self, op: &'a Op) -> Vec<&'a Param> {
+        let mut params = op.params();
+        if let Some(written) = op.written_param() {
+            params.remove(written);
+        }
+
+        let skips_target = match op {
+            Op::JumpIfTrue(test, _) => self.read_parameter(*test) == Ok(0),
+            Op::JumpIfFalse(test, _) => matches!(self.read_parameter(*test), Ok(val) if val != 0),
+            _ => false,
+        };
+        if skips_target {
+            params.pop();
+        }
+
+        params
+    }
+
+    /// The memory address a parameter refers to right now, or `None` for
+    /// immediate values and addresses that are out of range.
+    pub fn address_of(&self, param: &Param) -> Option<usize> {
+        match param {
+            Param::ImmediateMode(_) => None,
+            address => self.resolve_address(*address).ok(),
+        }
+    }
+
+    fn fault(&self, kind: ErrorKind) -> IntcodeError {
+        IntcodeError::new(
+            self.instruction_pointer,
+            self.memory.read(self.instruction_pointer),
+            kind,
+        )
+    }
+
+    fn read_parameter(&self, param: Param) -> Result<isize, ErrorKind> {
+        match param {
+            Param::ImmediateMode(val) => Ok(val),
+            address => Ok(self.memory.read(self.resolve_address(address)?)),
+        }
+    }
+
+    fn write_parameter(&mut self, param: Param, val: isize) -> Result<(), ErrorKind> {
+        let at = self.resolve_address(param)?;
+        if let Some(monitor) = self.code_monitor.as_mut() {
+            monitor.check(self.instruction_pointer, at, self.memory.read(at), val)?;
+        }
+
+        self.memory.write(at, val)
+    }
+
+    fn jump_target(&self, param: Param) -> Result<usize, ErrorKind> {
+        match self.read_parameter(param)? {
+            ip if ip < 0 => Err(ErrorKind::InvalidAddress(ip)),
+            ip => Ok(ip as usize),
+        }
+    }
+
+    fn resolve_address(&self, param: Param) -> Result<usize, ErrorKind> {
         match param {
-            Param::PositionMode(at) => self.memory.read(at),
-            Param::ImmediateMode(val) => val,
+            Param::PositionMode(at) => Ok(at),
+            Param::RelativeMode(offset) => match self.relative_base.checked_add(offset) {
+                Some(at) if at < 0 => Err(ErrorKind::InvalidAddress(at)),
+                Some(at) => Ok(at as usize),
+                None => Err(ErrorKind::ArithmeticOverflow),
+            },
+            // the parser never hands out immediate write parameters
+            Param::ImmediateMode(val) => Err(ErrorKind::InvalidAddress(val)),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::io::IterInput;
+    use super::super::DOUBLER;
     use super::*;
+    use std::sync::Mutex;
     #[test]
     fn test_execute() {
         let programs: Vec<(Vec<isize>, Vec<isize>)> = vec![
@@ -152,15 +526,331 @@ mod tests {
 
         for (starting_memory, end_memory) in programs {
             let mut program = Interpreter::from_bytecode(&starting_memory);
-            program.execute();
+            program.execute().unwrap();
             assert_eq!(program.memory.dump(), end_memory)
         }
     }
 
     #[test]
     fn test_execute_2() {
-        let mut prg = Interpreter::from_string("1002,4,3,4,33");
-        prg.execute();
+        let mut prg = Interpreter::from_string("1002,4,3,4,33").unwrap();
+        prg.execute().unwrap();
         assert_eq!(prg.memory.dump(), vec![1002, 4, 3, 4, 99]);
     }
+
+    #[test]
+    fn test_execute_relative_mode() {
+        let programs: Vec<(Vec<isize>, Vec<isize>)> = vec![
+            (
+                vec![109, 7, 21101, 2, 3, 0, 99, 0],
+                vec![109, 7, 21101, 2, 3, 0, 99, 5],
+            ),
+            (
+                vec![109, 7, 22201, 0, 1, 2, 99, 3, 4, 0],
+                vec![109, 7, 22201, 0, 1, 2, 99, 3, 4, 7],
+            ),
+            (
+                vec![109, 10, 209, -1, 21201, -5, 1, -4, 99, 4, 0],
+                vec![109, 10, 209, -1, 21201, -5, 1, -4, 99, 4, 5],
+            ),
+        ];
+
+        for (starting_memory, end_memory) in programs {
+            let mut program = Interpreter::from_bytecode(&starting_memory);
+            program.execute().unwrap();
+            assert_eq!(program.memory.dump(), end_memory)
+        }
+    }
+
+    #[test]
+    fn test_execute_past_program_end() {
+        let mut program = Interpreter::from_bytecode(&[1101, 2, 3, 10, 1, 10, 12, 11, 99]);
+        program.execute().unwrap();
+        assert_eq!(program.read(10), 5);
+        assert_eq!(program.read(11), 5);
+        assert_eq!(program.read(1_000), 0);
+    }
+
+    #[test]
+    fn test_execute_memory_limit() {
+        let mut program = Interpreter::with_memory_limit(&[1101, 2, 3, 100, 99], 16);
+        assert_eq!(
+            program.execute(),
+            Err(IntcodeError::new(
+                0,
+                1101,
+                ErrorKind::MemoryLimitExceeded {
+                    address: 100,
+                    limit: 16
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_execute_with() {
+        // outputs 1 if the input is equal to 8, 0 otherwise
+        let src = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        for (input, expected) in [(8, 1), (7, 0)] {
+            let mut output = vec![];
+            let mut program = Interpreter::from_bytecode(&src);
+            program
+                .execute_with(&mut IterInput(Some(input).into_iter()), &mut output)
+                .unwrap();
+            assert_eq!(output, vec![expected]);
+        }
+    }
+
+    #[test]
+    fn test_execute_with_relative_base() {
+        // quine from the day 9 examples
+        let src = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut output = vec![];
+        Interpreter::from_bytecode(&src)
+            .execute_with(&mut IterInput(None.into_iter()), &mut output)
+            .unwrap();
+        assert_eq!(output, src);
+    }
+
+    #[test]
+    fn test_execute_with_no_input() {
+        let result = Interpreter::from_bytecode(&[1101, 0, 0, 0, 3, 0, 99])
+            .execute_with(&mut IterInput(None.into_iter()), &mut vec![]);
+        assert_eq!(
+            result,
+            Err(IntcodeError::new(4, 3, ErrorKind::InputExhausted))
+        );
+    }
+
+    #[test]
+    fn test_execute_faults() {
+        let programs: Vec<(Vec<isize>, IntcodeError)> = vec![
+            (
+                vec![1101, 1, 1, 0, 98],
+                IntcodeError::new(4, 98, ErrorKind::UnknownOpcode),
+            ),
+            (
+                vec![109, -5, 204, 1, 99],
+                IntcodeError::new(2, 204, ErrorKind::InvalidAddress(-4)),
+            ),
+            (
+                vec![1105, 1, -2],
+                IntcodeError::new(0, 1105, ErrorKind::InvalidAddress(-2)),
+            ),
+            (
+                vec![1102, isize::MAX, 2, 0, 99],
+                IntcodeError::new(0, 1102, ErrorKind::ArithmeticOverflow),
+            ),
+            (
+                vec![1101, isize::MIN, -1, 0, 99],
+                IntcodeError::new(0, 1101, ErrorKind::ArithmeticOverflow),
+            ),
+            (
+                vec![109, isize::MAX, 109, 1, 99],
+                IntcodeError::new(2, 109, ErrorKind::ArithmeticOverflow),
+            ),
+            (
+                vec![109, isize::MAX, 204, 1, 99],
+                IntcodeError::new(2, 204, ErrorKind::ArithmeticOverflow),
+            ),
+        ];
+
+        for (src, error) in programs {
+            let mut program = Interpreter::from_bytecode(&src);
+            let result = program.execute_with(&mut IterInput(None.into_iter()), &mut vec![]);
+            assert_eq!(result, Err(error));
+        }
+    }
+
+    #[test]
+    fn test_run_pauses_on_io() {
+        let mut program = Interpreter::from_bytecode(&DOUBLER);
+
+        assert_eq!(program.run(), Ok(Status::NeedsInput));
+        assert_eq!(program.run(), Ok(Status::NeedsInput));
+        assert_eq!(program.instruction_pointer(), 0);
+
+        program.push_input(21);
+        assert_eq!(program.run(), Ok(Status::Output(42)));
+        assert_eq!(program.run(), Ok(Status::NeedsInput));
+
+        program.push_input(3);
+        program.push_input(0);
+        assert_eq!(program.run(), Ok(Status::Output(6)));
+        assert_eq!(program.run(), Ok(Status::Halted));
+        assert_eq!(program.run(), Ok(Status::Halted));
+    }
+
+    #[test]
+    fn test_run_chained() {
+        // adds its input to the phase it was given first
+        let src = vec![3, 11, 3, 12, 1, 11, 12, 11, 4, 11, 99, 0, 0];
+        let mut first = Interpreter::from_bytecode(&src);
+        let mut second = Interpreter::from_bytecode(&src);
+        first.push_input(1);
+        second.push_input(10);
+
+        first.push_input(100);
+        let signal = match first.run().unwrap() {
+            Status::Output(val) => val,
+            status => panic!("unexpected {:?}", status),
+        };
+
+        second.push_input(signal);
+        assert_eq!(second.run(), Ok(Status::Output(111)));
+        assert_eq!(second.run(), Ok(Status::Halted));
+    }
+
+    #[test]
+    fn test_tracer() {
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+
+        impl Tracer for Recorder {
+            fn trace(&mut self, step: &TraceStep) {
+                self.0.lock().unwrap().push(step.to_string());
+            }
+        }
+
+        let lines = Arc::new(Mutex::new(vec![]));
+        let src = vec![
+            109, 2, 1105, 1, 6, 99, 3, 20, 1008, 20, 8, 21, 204, 19, 1106, 0, 5, 0, 0, 0, 0,
+        ];
+        let mut program = Interpreter::from_bytecode(&src);
+        program.set_tracer(Box::new(Recorder(Arc::clone(&lines))));
+
+        assert_eq!(program.run(), Ok(Status::NeedsInput));
+        program.push_input(8);
+        assert_eq!(program.run(), Ok(Status::Output(1)));
+        assert_eq!(program.run(), Ok(Status::Halted));
+
+        assert_eq!(
+            *lines.lock().unwrap(),
+            vec![
+                "0: arb #2 | args 2 | base 2",
+                "2: jnz #1, #6 | args 1 6 | jump 6",
+                "6: in [20] | input 8 | write 20 8",
+                "8: eq [20], #8, [21] | args 8 8 | write 21 1",
+                "12: out rb+19 | args 1 | output 1",
+                "14: jz #0, #5 | args 0 5 | jump 5",
+                "5: hlt",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_untaken_jump_target_not_read() {
+        struct Recorder(Arc<Mutex<Vec<TraceStep>>>);
+
+        impl Tracer for Recorder {
+            fn trace(&mut self, step: &TraceStep) {
+                self.0.lock().unwrap().push(step.clone());
+            }
+        }
+
+        // jnz [5], [6] with [5] holding 0
+        let steps = Arc::new(Mutex::new(vec![]));
+        let mut program = Interpreter::from_bytecode(&[5, 5, 6, 99, 0, 0, 3]);
+        program.set_tracer(Box::new(Recorder(Arc::clone(&steps))));
+        assert_eq!(program.run(), Ok(Status::Halted));
+
+        let steps = steps.lock().unwrap();
+        assert_eq!(steps[0].args, vec![0]);
+        assert_eq!(steps[0].reads, vec![5]);
+        assert_eq!(steps[0].jump, None);
+    }
+
+    #[test]
+    fn test_tracing_keeps_results() {
+        struct Discard;
+
+        impl Tracer for Discard {
+            fn trace(&mut self, _: &TraceStep) {}
+        }
+
+        let programs = vec![
+            // waits for input before its bad address matters
+            vec![203, -5, 99],
+            // never reads the bad target of the untaken jump
+            vec![2206, 0, -5, 99],
+            // writes over its own write parameter
+            vec![1101, 5, 5, 3, 99],
+            vec![109, -5, 204, 1, 99],
+        ];
+
+        for src in programs {
+            let mut untraced = Interpreter::from_bytecode(&src);
+            let mut traced = Interpreter::from_bytecode(&src);
+            traced.set_tracer(Box::new(Discard));
+            loop {
+                let status = untraced.run();
+                assert_eq!(traced.run(), status, "{:?}", src);
+                assert_eq!(traced.snapshot(), untraced.snapshot());
+                if !matches!(status, Ok(Status::Output(_))) {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_instruction_budget() {
+        let mut program = Interpreter::from_bytecode(&[1105, 1, 0]);
+        program.set_instruction_budget(Some(10));
+        assert_eq!(
+            program.execute(),
+            Err(IntcodeError::new(0, 1105, ErrorKind::BudgetExhausted))
+        );
+        assert_eq!(program.instructions_executed(), 10);
+
+        // the halt doesn't count against the budget
+        let mut program = Interpreter::from_bytecode(&[1101, 1, 1, 0, 99]);
+        program.set_instruction_budget(Some(1));
+        assert_eq!(program.execute(), Ok(()));
+
+        // nor does waiting for input
+        let mut program = Interpreter::from_bytecode(&[3, 0, 99]);
+        program.set_instruction_budget(Some(0));
+        assert_eq!(program.run(), Ok(Status::NeedsInput));
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let mut program = Interpreter::from_bytecode(&[1105, 1, 0]);
+        program.set_time_limit(Some(Duration::from_millis(0)));
+        assert_eq!(
+            program.execute(),
+            Err(IntcodeError::new(0, 1105, ErrorKind::TimeLimitExceeded))
+        );
+    }
+
+    #[test]
+    fn test_loop_detection() {
+        // counts down from 3 and halts, then the same loop without the halt
+        let countdown = vec![1001, 9, -1, 9, 1005, 9, 0, 99, 0, 3];
+        let mut program = Interpreter::from_bytecode(&countdown);
+        program.set_loop_detection(true);
+        assert_eq!(program.execute(), Ok(()));
+
+        let mut stuck = countdown.clone();
+        stuck[2] = 0;
+        let mut program = Interpreter::from_bytecode(&stuck);
+        program.set_loop_detection(true);
+        assert_eq!(
+            program.execute(),
+            Err(IntcodeError::new(0, 1001, ErrorKind::InfiniteLoop))
+        );
+
+        // echoes its input forever, which only looks stuck without the input
+        let mut program = Interpreter::from_bytecode(&[3, 7, 4, 7, 1105, 1, 0, 0]);
+        program.set_loop_detection(true);
+        let mut output = vec![];
+        let result = program.execute_with(&mut IterInput(vec![1, 1, 1].into_iter()), &mut output);
+        assert_eq!(
+            result,
+            Err(IntcodeError::new(0, 3, ErrorKind::InputExhausted))
+        );
+        assert_eq!(output, vec![1, 1, 1]);
+    }
 }
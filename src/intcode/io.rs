@@ -0,0 +1,139 @@
+use std::collections::VecDeque;
+use std::io::prelude::*;
+use std::io::{stdin, stdout};
+use std::sync::mpsc::{Receiver, Sender};
+
+/// Where an `Op::Input` instruction takes its value from.
+pub trait InputSource {
+    /// The next input word, or `None` if there is nothing left to read.
+    fn read_input(&mut self) -> Option<isize>;
+}
+
+/// Where an `Op::Output` instruction sends its value.
+pub trait OutputSink {
+    fn write_output(&mut self, val: isize);
+}
+
+/// Reads one integer per line from the terminal, asking again whenever a
+/// line doesn't parse.
+pub struct StdinInput;
+
+impl InputSource for StdinInput {
+    fn read_input(&mut self) -> Option<isize> {
+        loop {
+            let mut line = String::new();
+            match stdin().read_line(&mut line) {
+                Ok(0) | Err(_) => return None,
+                Ok(_) => match line.trim().parse() {
+                    Ok(val) => return Some(val),
+                    Err(_) => eprintln!("[{}] is not a valid input", line.trim()),
+                },
+            }
+        }
+    }
+}
+
+/// Writes every output on its own line to the terminal.
+pub struct StdoutOutput;
+
+impl OutputSink for StdoutOutput {
+    fn write_output(&mut self, val: isize) {
+        if writeln!(stdout(), "{}", val).is_ok() {
+            //
+        }
+    }
+}
+
+/// Feeds the words of any iterator as input.
+pub struct IterInput<I: Iterator<Item = isize>>(pub I);
+
+impl<I: Iterator<Item = isize>> InputSource for IterInput<I> {
+    fn read_input(&mut self) -> Option<isize> {
+        self.0.next()
+    }
+}
+
+impl InputSource for VecDeque<isize> {
+    fn read_input(&mut self) -> Option<isize> {
+        self.pop_front()
+    }
+}
+
+impl OutputSink for Vec<isize> {
+    fn write_output(&mut self, val: isize) {
+        self.push(val);
+    }
+}
+
+/// Asks a closure for every input word.
+pub struct FnInput<F: FnMut() -> Option<isize>>(pub F);
+
+impl<F: FnMut() -> Option<isize>> InputSource for FnInput<F> {
+    fn read_input(&mut self) -> Option<isize> {
+        (self.0)()
+    }
+}
+
+/// Hands every output word to a closure.
+pub struct FnOutput<F: FnMut(isize)>(pub F);
+
+impl<F: FnMut(isize)> OutputSink for FnOutput<F> {
+    fn write_output(&mut self, val: isize) {
+        (self.0)(val)
+    }
+}
+
+// blocks until a value arrives, runs out of input once every sender is gone
+impl InputSource for Receiver<isize> {
+    fn read_input(&mut self) -> Option<isize> {
+        self.recv().ok()
+    }
+}
+
+// outputs sent after the receiving end hung up are dropped
+impl OutputSink for Sender<isize> {
+    fn write_output(&mut self, val: isize) {
+        if self.send(val).is_ok() {
+            //
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_iter_input() {
+        let mut input = IterInput(vec![1, 2].into_iter());
+        assert_eq!(input.read_input(), Some(1));
+        assert_eq!(input.read_input(), Some(2));
+        assert_eq!(input.read_input(), None);
+    }
+
+    #[test]
+    fn test_fn_input_output() {
+        let mut next = 0;
+        let mut input = FnInput(|| {
+            next += 1;
+            Some(next)
+        });
+        assert_eq!(input.read_input(), Some(1));
+        assert_eq!(input.read_input(), Some(2));
+
+        let mut seen = vec![];
+        let mut output = FnOutput(|val| seen.push(val * 2));
+        output.write_output(21);
+        assert_eq!(seen, vec![42]);
+    }
+
+    #[test]
+    fn test_channel_input_output() {
+        let (mut tx, mut rx) = channel();
+        tx.write_output(5);
+        drop(tx);
+        assert_eq!(rx.read_input(), Some(5));
+        assert_eq!(rx.read_input(), None);
+    }
+}
@@ -1,48 +1,125 @@
-use std::cell::RefCell;
+use std::sync::RwLock;
+
+use super::error::ErrorKind;
+
+/// Hard cap on the number of words a program may address, unless overridden
+/// with [`MemoryManager::with_limit`].
+pub const DEFAULT_MEMORY_LIMIT: usize = 1 << 22;
 
 pub struct MemoryManager {
-    memory: RefCell<Vec<isize>>,
+    memory: RwLock<Vec<isize>>,
+    limit: usize,
 }
 
-pub trait ReadOnlyMemoryManager {
+/// Memory is shared between the interpreter and its parser, and both may
+/// move to another thread with it, hence the `Send + Sync` bound.
+pub trait ReadOnlyMemoryManager: Send + Sync {
     fn read(&self, at: usize) -> isize;
-    fn read_address(&self, at: usize) -> usize;
+    fn read_address(&self, at: usize) -> Result<usize, ErrorKind>;
     fn dump(&self) -> Vec<isize>;
+    fn limit(&self) -> usize;
 }
 
 pub trait MutableMemoryManager: ReadOnlyMemoryManager {
-    fn write(&self, at: usize, val: isize);
+    fn write(&self, at: usize, val: isize) -> Result<(), ErrorKind>;
 }
 
 impl MemoryManager {
-    pub fn new(init: &Vec<isize>) -> MemoryManager {
-        let memory = RefCell::new(init.clone());
-        MemoryManager { memory }
+    pub fn new(init: &[isize]) -> MemoryManager {
+        MemoryManager::with_limit(init, DEFAULT_MEMORY_LIMIT)
+    }
+
+    /// Memory grows on demand past the loaded program, reading zero where
+    /// nothing was written, but never beyond `limit` words.
+    pub fn with_limit(init: &[isize], limit: usize) -> MemoryManager {
+        let memory = RwLock::new(init.to_vec());
+        MemoryManager {
+            memory,
+            limit: limit.max(init.len()),
+        }
     }
 }
 
 impl ReadOnlyMemoryManager for MemoryManager {
+    // nothing can ever be written past the limit, so reading there is just zero
     fn read(&self, at: usize) -> isize {
-        self.memory.borrow()[at]
+        self.memory.read().unwrap().get(at).copied().unwrap_or(0)
     }
 
-    fn read_address(&self, at: usize) -> usize {
+    fn read_address(&self, at: usize) -> Result<usize, ErrorKind> {
         let pos = self.read(at);
 
-        if pos < 0 || (pos as usize) >= self.memory.borrow().len() {
-            panic!(format!("Invalid memory referenced {}", pos));
+        if pos < 0 {
+            return Err(ErrorKind::InvalidAddress(pos));
         }
 
-        pos as usize
+        Ok(pos as usize)
     }
 
     fn dump(&self) -> Vec<isize> {
-        self.memory.borrow().clone()
+        self.memory.read().unwrap().clone()
+    }
+
+    fn limit(&self) -> usize {
+        self.limit
     }
 }
 
 impl MutableMemoryManager for MemoryManager {
-    fn write(&self, at: usize, val: isize) {
-        self.memory.borrow_mut()[at] = val;
+    fn write(&self, at: usize, val: isize) -> Result<(), ErrorKind> {
+        if at >= self.limit {
+            return Err(ErrorKind::MemoryLimitExceeded {
+                address: at,
+                limit: self.limit,
+            });
+        }
+
+        let mut memory = self.memory.write().unwrap();
+        if at >= memory.len() {
+            memory.resize(at + 1, 0);
+        }
+
+        memory[at] = val;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_past_end() {
+        let memory = MemoryManager::new(&[1, 2, 3]);
+        assert_eq!(memory.read(2), 3);
+        assert_eq!(memory.read(1000), 0);
+        assert_eq!(memory.dump(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_write_past_end() {
+        let memory = MemoryManager::new(&[1, 2, 3]);
+        memory.write(5, 7).unwrap();
+        assert_eq!(memory.read(5), 7);
+        assert_eq!(memory.dump(), vec![1, 2, 3, 0, 0, 7]);
+    }
+
+    #[test]
+    fn test_write_past_limit() {
+        let memory = MemoryManager::with_limit(&[1, 2, 3], 8);
+        assert_eq!(memory.write(7, 1), Ok(()));
+        assert_eq!(
+            memory.write(8, 1),
+            Err(ErrorKind::MemoryLimitExceeded {
+                address: 8,
+                limit: 8
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_negative_address() {
+        let memory = MemoryManager::new(&[-4]);
+        assert_eq!(memory.read_address(0), Err(ErrorKind::InvalidAddress(-4)));
     }
 }
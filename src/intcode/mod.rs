@@ -1,8 +1,66 @@
+mod adventure;
+mod amplifier;
+mod arcade;
+mod ascii;
+mod assembler;
+mod cfg;
+mod debugger;
+mod disassembler;
+mod error;
+mod fast;
+mod fuzz;
+mod image;
 mod interpreter;
+mod io;
 mod memory;
+mod monitor;
+mod network;
 mod parser;
+mod profile;
+mod robot;
+mod snapshot;
+mod symbolic;
+mod threaded;
+mod trace;
 
-pub use interpreter::Interpreter;
+/// Doubles every input until it reads a 0. Assembled in the assembler's
+/// tests and run by most of the others.
+#[cfg(test)]
+pub(crate) const DOUBLER: [isize; 16] = [
+    3, 15, 1006, 15, 14, 1002, 15, 2, 15, 4, 15, 1105, 1, 0, 99, 0,
+];
+
+pub use adventure::{
+    explore, look_around, parse_room, play, Exploration, ExploreError, Game, Room,
+};
+pub use amplifier::{max_signal, permutations, AmplifierChain};
+pub use arcade::{Arcade, AutoPlayer, Joystick, Neutral, Screen, StdinJoystick, Tile};
+pub use ascii::{AsciiComputer, AsciiOutput, AsciiStdinInput, AsciiStdoutOutput};
+pub use assembler::{assemble, AssembleError};
+pub use cfg::{control_flow_graph, Block, Cfg, Exit};
+pub use debugger::{Access, Debugger, Stop, Watch};
+pub use disassembler::{disassemble, listing, Line};
+pub use error::{ErrorKind, IntcodeError, ParseError};
+pub use fast::FastInterpreter;
+pub use fuzz::{
+    compare, fast_interpreter, fuzz, generate, interpreter, shrink, Case, Ending, Engine, Mismatch,
+    Outcome, XorShift, FUZZ_BUDGET, FUZZ_MEMORY_LIMIT,
+};
+pub use image::{is_image, parse_program, Image, ImageError, Metadata, IMAGE_MAGIC, IMAGE_VERSION};
+pub use interpreter::{Interpreter, Status};
+pub use io::{FnInput, FnOutput, InputSource, IterInput, OutputSink, StdinInput, StdoutOutput};
+pub use memory::{
+    MemoryManager, MutableMemoryManager, ReadOnlyMemoryManager, DEFAULT_MEMORY_LIMIT,
+};
+pub use monitor::{CodeMonitor, CodeWrite, OnCodeWrite};
+pub use network::{Device, Nat, Network, Packet, NAT_ADDRESS};
+pub use parser::{parse_bytecode_string, to_bytecode_string, Op, Param, Parser};
+pub use profile::{Hits, Profile};
+pub use robot::{Brain, Cell, Color, Direction, Grid, PaintingRobot, Point, Pose, RepairDroid};
+pub use snapshot::{Snapshot, SnapshotError, SNAPSHOT_VERSION};
+pub use symbolic::{solve, Expr, Polynomial, SolveError, SymbolicError, SymbolicMachine};
+pub use threaded::{spawn_with, ThreadedVm};
+pub use trace::{TraceStep, TraceWriter, Tracer, TRACE_HEADER};
 
 // #[cfg(test)]
 // mod tests {
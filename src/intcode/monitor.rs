@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use super::error::ErrorKind;
+
+/// What to do when a program writes over code it has already executed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnCodeWrite {
+    /// Let the write happen and remember it.
+    Report,
+    /// Fail with `ErrorKind::CodeOverwritten` before anything is written.
+    Trap,
+}
+
+/// An instruction at `ip` writing over a word that was executed as code.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeWrite {
+    pub ip: usize,
+    pub address: usize,
+    pub old: isize,
+    pub new: isize,
+}
+
+impl fmt::Display for CodeWrite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "instruction {} overwrote code at {}: {} -> {}",
+            self.ip, self.address, self.old, self.new
+        )
+    }
+}
+
+/// Keeps track of every word executed as part of an instruction, opcode
+/// and parameters alike, and of the writes that land on one of them.
+pub struct CodeMonitor {
+    on_write: OnCodeWrite,
+    executed: HashSet<usize>,
+    writes: Vec<CodeWrite>,
+}
+
+impl CodeMonitor {
+    pub fn new(on_write: OnCodeWrite) -> CodeMonitor {
+        CodeMonitor {
+            on_write,
+            executed: HashSet::new(),
+            writes: vec![],
+        }
+    }
+
+    pub fn mark_executed(&mut self, at: usize, size: usize) {
+        self.executed.extend(at..at + size);
+    }
+
+    pub fn is_code(&self, at: usize) -> bool {
+        self.executed.contains(&at)
+    }
+
+    /// Called before the instruction at `ip` writes `new` over `old` at
+    /// `address`.
+    pub fn check(
+        &mut self,
+        ip: usize,
+        address: usize,
+        old: isize,
+        new: isize,
+    ) -> Result<(), ErrorKind> {
+        if !self.is_code(address) {
+            return Ok(());
+        }
+
+        if self.on_write == OnCodeWrite::Trap {
+            return Err(ErrorKind::CodeOverwritten { address, old, new });
+        }
+
+        self.writes.push(CodeWrite {
+            ip,
+            address,
+            old,
+            new,
+        });
+        Ok(())
+    }
+
+    pub fn writes(&self) -> &[CodeWrite] {
+        &self.writes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::interpreter::{Interpreter, Status};
+    use super::super::DOUBLER;
+    use super::*;
+
+    // prints 7, then patches the `out #7` it just ran into `out #8` and
+    // loops back to it
+    const PATCHER: [isize; 9] = [104, 7, 1101, 0, 8, 1, 1105, 1, 0];
+
+    #[test]
+    fn test_report() {
+        let mut program = Interpreter::from_bytecode(&PATCHER);
+        program.set_code_monitor(Some(OnCodeWrite::Report));
+
+        assert_eq!(program.run(), Ok(Status::Output(7)));
+        assert_eq!(program.run(), Ok(Status::Output(8)));
+        assert_eq!(
+            program.code_writes(),
+            &[CodeWrite {
+                ip: 2,
+                address: 1,
+                old: 7,
+                new: 8
+            }]
+        );
+        assert_eq!(
+            program.code_writes()[0].to_string(),
+            "instruction 2 overwrote code at 1: 7 -> 8"
+        );
+    }
+
+    #[test]
+    fn test_trap() {
+        let mut program = Interpreter::from_bytecode(&PATCHER);
+        program.set_code_monitor(Some(OnCodeWrite::Trap));
+
+        assert_eq!(program.run(), Ok(Status::Output(7)));
+        assert_eq!(
+            program
+                .run()
+                .map_err(|err| (err.instruction_pointer, err.kind)),
+            Err((
+                2,
+                ErrorKind::CodeOverwritten {
+                    address: 1,
+                    old: 7,
+                    new: 8
+                }
+            ))
+        );
+        assert_eq!(program.read(1), 7);
+    }
+
+    #[test]
+    fn test_data_writes_are_fine() {
+        let mut program = Interpreter::from_bytecode(&DOUBLER);
+        program.set_code_monitor(Some(OnCodeWrite::Trap));
+        program.push_input(21);
+        program.push_input(0);
+
+        assert_eq!(program.run(), Ok(Status::Output(42)));
+        assert_eq!(program.run(), Ok(Status::Halted));
+        assert!(program.code_writes().is_empty());
+    }
+}
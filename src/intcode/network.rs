@@ -0,0 +1,303 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use super::error::IntcodeError;
+use super::interpreter::{Interpreter, Status};
+
+/// NAT address from the 2019 network puzzle.
+pub const NAT_ADDRESS: usize = 255;
+
+// full rounds without any packet moving before the network counts as idle
+const IDLE_ROUNDS: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Packet {
+    pub to: usize,
+    pub x: isize,
+    pub y: isize,
+}
+
+/// Something listening on an address that isn't one of the computers.
+pub trait Device {
+    /// Called for every packet sent to the device's address. Returning a
+    /// value stops the network with it.
+    fn receive(&mut self, packet: Packet) -> Option<isize>;
+
+    /// Called whenever the network goes idle. Packets pushed to `outbox` are
+    /// delivered like any other; returning a value stops the network with it.
+    fn on_idle(&mut self, _outbox: &mut Vec<Packet>) -> Option<isize> {
+        None
+    }
+}
+
+/// Remembers the last packet it got and sends it to computer 0 whenever the
+/// network goes idle, stopping the network with the first `y` it delivers
+/// twice in a row.
+#[derive(Default)]
+pub struct Nat {
+    last: Option<Packet>,
+    last_delivered_y: Option<isize>,
+}
+
+impl Device for Nat {
+    fn receive(&mut self, packet: Packet) -> Option<isize> {
+        self.last = Some(packet);
+        None
+    }
+
+    fn on_idle(&mut self, outbox: &mut Vec<Packet>) -> Option<isize> {
+        let packet = self.last?;
+        if self.last_delivered_y == Some(packet.y) {
+            return Some(packet.y);
+        }
+
+        self.last_delivered_y = Some(packet.y);
+        outbox.push(Packet { to: 0, ..packet });
+        None
+    }
+}
+
+/// Many copies of a program exchanging `(address, x, y)` packets.
+///
+/// Computers are scheduled round-robin in address order: on its turn a
+/// computer gets the next packet queued for it, or `-1` if there is none, and
+/// runs until it asks for input again. Every output triple it produced is
+/// routed as it completes. Scheduling never depends on timing, so a network
+/// always plays out the same way.
+pub struct Network {
+    computers: Vec<Interpreter>,
+    halted: Vec<bool>,
+    queues: Vec<VecDeque<Packet>>,
+    pending_output: Vec<Vec<isize>>,
+    devices: BTreeMap<usize, Box<dyn Device>>,
+}
+
+impl Network {
+    /// Boots `size` computers running `program`, each given its address as
+    /// its first input.
+    pub fn new(program: &[isize], size: usize) -> Network {
+        let computers = (0..size)
+            .map(|address| {
+                let mut computer = Interpreter::from_bytecode(program);
+                computer.push_input(address as isize);
+                computer
+            })
+            .collect();
+
+        Network {
+            computers,
+            halted: vec![false; size],
+            queues: vec![VecDeque::new(); size],
+            pending_output: vec![vec![]; size],
+            devices: BTreeMap::new(),
+        }
+    }
+
+    /// Routes packets sent to `address` to `device` instead of dropping them.
+    pub fn attach(&mut self, address: usize, device: Box<dyn Device>) {
+        self.devices.insert(address, device);
+    }
+
+    /// Queues a packet as if some computer had sent it.
+    pub fn send(&mut self, packet: Packet) -> Option<isize> {
+        if packet.to < self.queues.len() {
+            self.queues[packet.to].push_back(packet);
+            None
+        } else if let Some(device) = self.devices.get_mut(&packet.to) {
+            device.receive(packet)
+        } else {
+            // nobody listens on that address
+            None
+        }
+    }
+
+    /// Runs the network until a device stops it with a value. Returns `None`
+    /// if every computer halts, or if the network goes idle and no device
+    /// wakes it back up.
+    pub fn run(&mut self) -> Result<Option<isize>, IntcodeError> {
+        let mut idle_rounds = 0;
+
+        loop {
+            let mut active = false;
+
+            for address in 0..self.computers.len() {
+                if self.halted[address] {
+                    continue;
+                }
+
+                let (sent, received) = self.turn(address)?;
+                active = active || received || !sent.is_empty();
+                for packet in sent {
+                    if let Some(result) = self.send(packet) {
+                        return Ok(Some(result));
+                    }
+                }
+            }
+
+            if self.halted.iter().all(|&halted| halted) {
+                return Ok(None);
+            }
+
+            idle_rounds = if active { 0 } else { idle_rounds + 1 };
+            if idle_rounds < IDLE_ROUNDS {
+                continue;
+            }
+
+            let mut outbox = vec![];
+            for device in self.devices.values_mut() {
+                if let Some(result) = device.on_idle(&mut outbox) {
+                    return Ok(Some(result));
+                }
+            }
+
+            if outbox.is_empty() {
+                return Ok(None);
+            }
+
+            idle_rounds = 0;
+            for packet in outbox {
+                if let Some(result) = self.send(packet) {
+                    return Ok(Some(result));
+                }
+            }
+        }
+    }
+
+    // gives the computer one packet, or -1, and runs it until it needs more
+    // input, returning the packets it sent and whether it got a packet
+    fn turn(&mut self, address: usize) -> Result<(Vec<Packet>, bool), IntcodeError> {
+        let computer = &mut self.computers[address];
+        let received = match self.queues[address].pop_front() {
+            Some(packet) => {
+                computer.push_input(packet.x);
+                computer.push_input(packet.y);
+                true
+            }
+            None => {
+                computer.push_input(-1);
+                false
+            }
+        };
+
+        let mut sent = vec![];
+        loop {
+            match computer.run()? {
+                Status::Output(val) => {
+                    let output = &mut self.pending_output[address];
+                    output.push(val);
+                    if output.len() == 3 {
+                        if output[0] >= 0 {
+                            sent.push(Packet {
+                                to: output[0] as usize,
+                                x: output[1],
+                                y: output[2],
+                            });
+                        }
+                        output.clear();
+                    }
+                }
+                Status::NeedsInput => break,
+                Status::Halted => {
+                    self.halted[address] = true;
+                    break;
+                }
+            }
+        }
+
+        Ok((sent, received))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::assembler::assemble;
+    use super::*;
+
+    struct Collector(Vec<Packet>, usize);
+
+    impl Device for Collector {
+        fn receive(&mut self, packet: Packet) -> Option<isize> {
+            self.0.push(packet);
+            if self.0.len() == self.1 {
+                Some(packet.x)
+            } else {
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn test_packets_are_routed() {
+        // passes every packet on to the next address with x incremented
+        let forwarder = assemble(
+            "
+                    in [addr]
+            loop:   in [x]
+                    eq [x], #-1, [tmp]
+                    jnz [tmp], #loop
+                    in [y]
+                    add [addr], #1, [tmp]
+                    out [tmp]
+                    add [x], #1, [x]
+                    out [x]
+                    out [y]
+                    jnz #1, #loop
+            addr:   data 0
+            x:      data 0
+            y:      data 0
+            tmp:    data 0
+            ",
+        )
+        .unwrap();
+
+        let mut network = Network::new(&forwarder, 3);
+        network.attach(3, Box::new(Collector(vec![], 2)));
+        network.send(Packet { to: 0, x: 0, y: 42 });
+        network.send(Packet {
+            to: 1,
+            x: 10,
+            y: 43,
+        });
+
+        assert_eq!(network.run(), Ok(Some(3)));
+    }
+
+    #[test]
+    fn test_nat() {
+        // computer 0 reports every packet to the NAT with y counted down to 0
+        let reporter = assemble(
+            "
+                    in [addr]
+            loop:   in [x]
+                    eq [x], #-1, [tmp]
+                    jnz [tmp], #loop
+                    in [y]
+                    jz [y], #send
+                    add [y], #-1, [y]
+            send:   out #255
+                    out [x]
+                    out [y]
+                    jnz #1, #loop
+            addr:   data 0
+            x:      data 0
+            y:      data 0
+            tmp:    data 0
+            ",
+        )
+        .unwrap();
+
+        let mut network = Network::new(&reporter, 2);
+        network.attach(NAT_ADDRESS, Box::new(Nat::default()));
+        network.send(Packet { to: 0, x: 7, y: 3 });
+
+        assert_eq!(network.run(), Ok(Some(0)));
+    }
+
+    #[test]
+    fn test_idle_without_devices() {
+        let mut network = Network::new(&[3, 5, 1105, 1, 0, 0], 4);
+        assert_eq!(network.run(), Ok(None));
+
+        let mut network = Network::new(&[3, 3, 99, 0], 4);
+        assert_eq!(network.run(), Ok(None));
+    }
+}
@@ -1,111 +1,310 @@
+use super::error::{ErrorKind, IntcodeError, ParseError};
 use super::memory::ReadOnlyMemoryManager;
-use std::rc::Rc;
+use std::fmt;
+use std::sync::Arc;
 
 type PositionMode = usize;
 type ImmediateMode = isize;
+type RelativeMode = isize;
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Op {
-    Sum(Param, Param, PositionMode),
-    Multiply(Param, Param, PositionMode),
+    Sum(Param, Param, Param),
+    Multiply(Param, Param, Param),
 
-    LessThan(Param, Param, PositionMode),
-    Equals(Param, Param, PositionMode),
+    LessThan(Param, Param, Param),
+    Equals(Param, Param, Param),
 
     JumpIfTrue(Param, Param),
     JumpIfFalse(Param, Param),
 
-    Input(PositionMode),
-    Output(PositionMode),
+    Input(Param),
+    Output(Param),
+
+    AdjustRelativeBase(Param),
     Halt,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Param {
     PositionMode(PositionMode),
     ImmediateMode(ImmediateMode),
+    RelativeMode(RelativeMode),
+}
+
+impl Op {
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Op::Sum(..) => "add",
+            Op::Multiply(..) => "mul",
+            Op::LessThan(..) => "lt",
+            Op::Equals(..) => "eq",
+            Op::JumpIfTrue(..) => "jnz",
+            Op::JumpIfFalse(..) => "jz",
+            Op::Input(..) => "in",
+            Op::Output(..) => "out",
+            Op::AdjustRelativeBase(..) => "arb",
+            Op::Halt => "hlt",
+        }
+    }
+
+    pub fn params(&self) -> Vec<&Param> {
+        match self {
+            Op::Sum(a, b, c)
+            | Op::Multiply(a, b, c)
+            | Op::LessThan(a, b, c)
+            | Op::Equals(a, b, c) => {
+                vec![a, b, c]
+            }
+            Op::JumpIfTrue(a, b) | Op::JumpIfFalse(a, b) => vec![a, b],
+            Op::Input(a) | Op::Output(a) | Op::AdjustRelativeBase(a) => vec![a],
+            Op::Halt => vec![],
+        }
+    }
+
+    /// Index into `params` of the parameter the instruction writes to.
+    pub fn written_param(&self) -> Option<usize> {
+        match self {
+            Op::Sum(..) | Op::Multiply(..) | Op::LessThan(..) | Op::Equals(..) => Some(2),
+            Op::Input(..) => Some(0),
+            _ => None,
+        }
+    }
+
+    /// Number of words the instruction takes up, opcode included.
+    pub fn size(&self) -> usize {
+        self.params().len() + 1
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.mnemonic())?;
+        for (i, param) in self.params().iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, param)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Param::PositionMode(at) => write!(f, "[{}]", at),
+            Param::ImmediateMode(val) => write!(f, "#{}", val),
+            Param::RelativeMode(offset) if *offset < 0 => write!(f, "rb{}", offset),
+            Param::RelativeMode(offset) => write!(f, "rb+{}", offset),
+        }
+    }
 }
 
 pub struct Parser {
-    memory: Rc<dyn ReadOnlyMemoryManager>,
+    memory: Arc<dyn ReadOnlyMemoryManager>,
 }
 
 impl Parser {
-    pub fn new(memory: Rc<dyn ReadOnlyMemoryManager>) -> Parser {
+    pub fn new(memory: Arc<dyn ReadOnlyMemoryManager>) -> Parser {
         Parser { memory }
     }
 
-    pub fn parse_op(&self, at: usize) -> Op {
-        let op_code = self.memory.read(at) % 100;
-        let param_modes = (self.memory.read(at) / 100) as usize;
+    pub fn parse_op(&self, at: usize) -> Result<Op, IntcodeError> {
+        let word = self.memory.read(at);
+        self.decode(at, word)
+            .map_err(|kind| IntcodeError::new(at, word, kind))
+    }
+
+    fn decode(&self, at: usize, word: isize) -> Result<Op, ErrorKind> {
+        let op_code = word % 100;
+        let param_modes = (word / 100) as usize;
 
-        match op_code {
+        let op = match op_code {
             99 => Op::Halt,
 
             1 => Op::Sum(
-                self.read_parameter(at + 1, param_modes % 10),
-                self.read_parameter(at + 2, param_modes / 10 % 10),
-                self.memory.read_address(at + 3),
+                self.read_parameter(at, 1, param_modes)?,
+                self.read_parameter(at, 2, param_modes)?,
+                self.write_parameter(at, 3, param_modes)?,
             ),
 
             2 => Op::Multiply(
-                self.read_parameter(at + 1, param_modes % 10),
-                self.read_parameter(at + 2, param_modes / 10 % 10),
-                self.memory.read_address(at + 3),
+                self.read_parameter(at, 1, param_modes)?,
+                self.read_parameter(at, 2, param_modes)?,
+                self.write_parameter(at, 3, param_modes)?,
             ),
 
-            3 => Op::Input(self.memory.read_address(at + 1)),
+            3 => Op::Input(self.write_parameter(at, 1, param_modes)?),
 
-            4 => Op::Output(self.memory.read_address(at + 1)),
+            4 => Op::Output(self.read_parameter(at, 1, param_modes)?),
 
             5 => Op::JumpIfTrue(
-                self.read_parameter(at + 1, param_modes % 10),
-                self.read_parameter(at + 2, param_modes / 10 % 10),
+                self.read_parameter(at, 1, param_modes)?,
+                self.read_parameter(at, 2, param_modes)?,
             ),
 
             6 => Op::JumpIfFalse(
-                self.read_parameter(at + 1, param_modes % 10),
-                self.read_parameter(at + 2, param_modes / 10 % 10),
+                self.read_parameter(at, 1, param_modes)?,
+                self.read_parameter(at, 2, param_modes)?,
             ),
 
             7 => Op::LessThan(
-                self.read_parameter(at + 1, param_modes % 10),
-                self.read_parameter(at + 2, param_modes / 10 % 10),
-                self.memory.read_address(at + 3),
+                self.read_parameter(at, 1, param_modes)?,
+                self.read_parameter(at, 2, param_modes)?,
+                self.write_parameter(at, 3, param_modes)?,
             ),
 
             8 => Op::Equals(
-                self.read_parameter(at + 1, param_modes % 10),
-                self.read_parameter(at + 2, param_modes / 10 % 10),
-                self.memory.read_address(at + 3),
+                self.read_parameter(at, 1, param_modes)?,
+                self.read_parameter(at, 2, param_modes)?,
+                self.write_parameter(at, 3, param_modes)?,
             ),
 
-            x => panic!(format!("Unknown op code {}", x)),
+            9 => Op::AdjustRelativeBase(self.read_parameter(at, 1, param_modes)?),
+
+            _ => return Err(ErrorKind::UnknownOpcode),
+        };
+
+        Ok(op)
+    }
+
+    // `param` is the 1-based index of the parameter after the opcode word
+    fn read_parameter(&self, at: usize, param: usize, modes: usize) -> Result<Param, ErrorKind> {
+        match mode_of(param, modes) {
+            0 => Ok(Param::PositionMode(self.memory.read_address(at + param)?)),
+            1 => Ok(Param::ImmediateMode(self.memory.read(at + param))),
+            2 => Ok(Param::RelativeMode(self.memory.read(at + param))),
+            mode => Err(ErrorKind::InvalidParameterMode { param, mode }),
         }
     }
 
-    fn read_parameter(&self, at: usize, flag: usize) -> Param {
-        if flag == 1 {
-            Param::ImmediateMode(self.memory.read(at))
-        } else {
-            Param::PositionMode(self.memory.read_address(at))
+    // parameters that an instruction writes to are never in immediate mode
+    fn write_parameter(&self, at: usize, param: usize, modes: usize) -> Result<Param, ErrorKind> {
+        match mode_of(param, modes) {
+            0 => Ok(Param::PositionMode(self.memory.read_address(at + param)?)),
+            2 => Ok(Param::RelativeMode(self.memory.read(at + param))),
+            mode => Err(ErrorKind::InvalidParameterMode { param, mode }),
         }
     }
 }
 
-pub fn parse_bytecode_string(src: &str) -> Vec<isize> {
-    src.split_whitespace()
-        .flat_map(|line| {
-            line.split(',').flat_map(|val| {
-                let word = val.parse::<isize>();
-                match word {
-                    Ok(val) => vec![val],
-                    Err(_) => {
-                        println!("[{}] did not produce a good value", val);
-                        vec![]
-                    }
-                }
+fn mode_of(param: usize, modes: usize) -> usize {
+    modes / 10usize.pow(param as u32 - 1) % 10
+}
+
+pub fn parse_bytecode_string(src: &str) -> Result<Vec<isize>, ParseError> {
+    src.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|val| !val.is_empty())
+        .enumerate()
+        .map(|(position, val)| {
+            val.parse::<isize>().map_err(|_| ParseError {
+                position,
+                token: String::from(val),
             })
         })
         .collect()
 }
+
+/// The inverse of [`parse_bytecode_string`]: words separated by commas.
+pub fn to_bytecode_string(src: &[isize]) -> String {
+    let words: Vec<String> = src.iter().map(|word| word.to_string()).collect();
+    words.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::memory::MemoryManager;
+    use super::*;
+
+    fn parser_for(src: Vec<isize>) -> Parser {
+        Parser::new(Arc::new(MemoryManager::new(&src)))
+    }
+
+    #[test]
+    fn test_parse_op_relative_mode() {
+        let parser = parser_for(vec![22201, 3, -1, 4, 0]);
+        assert_eq!(
+            parser.parse_op(0).unwrap(),
+            Op::Sum(
+                Param::RelativeMode(3),
+                Param::RelativeMode(-1),
+                Param::RelativeMode(4)
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_op_adjust_relative_base() {
+        assert_eq!(
+            parser_for(vec![109, -7]).parse_op(0).unwrap(),
+            Op::AdjustRelativeBase(Param::ImmediateMode(-7))
+        );
+        assert_eq!(
+            parser_for(vec![209, 1]).parse_op(0).unwrap(),
+            Op::AdjustRelativeBase(Param::RelativeMode(1))
+        );
+    }
+
+    #[test]
+    fn test_parse_op_output_immediate() {
+        assert_eq!(
+            parser_for(vec![104, 0]).parse_op(0).unwrap(),
+            Op::Output(Param::ImmediateMode(0))
+        );
+    }
+
+    #[test]
+    fn test_parse_op_errors() {
+        assert_eq!(
+            parser_for(vec![1, 2, 3, 4, 42]).parse_op(4),
+            Err(IntcodeError::new(4, 42, ErrorKind::UnknownOpcode))
+        );
+        assert_eq!(
+            parser_for(vec![301, 0, 0, 0]).parse_op(0),
+            Err(IntcodeError::new(
+                0,
+                301,
+                ErrorKind::InvalidParameterMode { param: 1, mode: 3 }
+            ))
+        );
+        assert_eq!(
+            parser_for(vec![11101, 0, 0, 0]).parse_op(0),
+            Err(IntcodeError::new(
+                0,
+                11101,
+                ErrorKind::InvalidParameterMode { param: 3, mode: 1 }
+            ))
+        );
+        assert_eq!(
+            parser_for(vec![4, -1]).parse_op(0),
+            Err(IntcodeError::new(0, 4, ErrorKind::InvalidAddress(-1)))
+        );
+    }
+
+    #[test]
+    fn test_parse_bytecode_string() {
+        assert_eq!(
+            parse_bytecode_string("1,0, 0,3\n99\n"),
+            Ok(vec![1, 0, 0, 3, 99])
+        );
+        assert_eq!(
+            parse_bytecode_string("1,x,99"),
+            Err(ParseError {
+                position: 1,
+                token: String::from("x")
+            })
+        );
+        assert_eq!(to_bytecode_string(&[1, 0, -3, 99]), "1,0,-3,99");
+    }
+
+    #[test]
+    fn test_display_op() {
+        let parser = parser_for(vec![1002, 224, 5, 224, 21101, 1, -2, 3, 204, -3]);
+        assert_eq!(
+            parser.parse_op(0).unwrap().to_string(),
+            "mul [224], #5, [224]"
+        );
+        assert_eq!(parser.parse_op(4).unwrap().to_string(), "add #1, #-2, rb+3");
+        assert_eq!(parser.parse_op(8).unwrap().to_string(), "out rb-3");
+        assert_eq!(Op::Halt.to_string(), "hlt");
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use super::disassembler::{disassemble, Line};
+use super::parser::Op;
+use super::trace::{TraceStep, Tracer};
+
+/// Counts where a program spends its time and which memory it touches.
+///
+/// It is a [`Tracer`], so it costs nothing until it is installed. To read
+/// the counts while the interpreter still owns it, share it through an
+/// `Arc<Mutex<Profile>>`:
+///
+/// ```
+/// use aocrs::intcode::{Interpreter, Profile};
+/// use std::sync::{Arc, Mutex};
+///
+/// let profile = Arc::new(Mutex::new(Profile::default()));
+/// let mut program = Interpreter::from_bytecode(&[1101, 2, 3, 5, 99, 0]);
+/// program.set_tracer(Box::new(Arc::clone(&profile)));
+/// program.run().unwrap();
+///
+/// // the add and the halt
+/// assert_eq!(profile.lock().unwrap().executed(), 2);
+/// ```
+#[derive(Debug, Default)]
+pub struct Profile {
+    executions: BTreeMap<usize, u64>,
+    reads: BTreeMap<usize, u64>,
+    writes: BTreeMap<usize, u64>,
+    opcodes: BTreeMap<&'static str, u64>,
+    executed: u64,
+    // running a halted program again traces the same halt again
+    halted: bool,
+}
+
+/// Hit counts for one address.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Hits {
+    pub executions: u64,
+    pub reads: u64,
+    pub writes: u64,
+}
+
+impl Hits {
+    fn total(&self) -> u64 {
+        self.executions + self.reads + self.writes
+    }
+}
+
+impl Tracer for Profile {
+    fn trace(&mut self, step: &TraceStep) {
+        if step.op == Op::Halt && self.halted {
+            return;
+        }
+        self.halted = step.op == Op::Halt;
+
+        self.executed += 1;
+        *self.executions.entry(step.ip).or_insert(0) += 1;
+        *self.opcodes.entry(step.op.mnemonic()).or_insert(0) += 1;
+        for &at in &step.reads {
+            *self.reads.entry(at).or_insert(0) += 1;
+        }
+        if let Some((at, _)) = step.write {
+            *self.writes.entry(at).or_insert(0) += 1;
+        }
+    }
+}
+
+impl Profile {
+    /// Instructions executed while profiling, the halt included, but only
+    /// once however often a halted program is run again.
+    pub fn executed(&self) -> u64 {
+        self.executed
+    }
+
+    pub fn hits(&self, at: usize) -> Hits {
+        let count = |map: &BTreeMap<usize, u64>| map.get(&at).copied().unwrap_or(0);
+        Hits {
+            executions: count(&self.executions),
+            reads: count(&self.reads),
+            writes: count(&self.writes),
+        }
+    }
+
+    pub fn opcode_count(&self, mnemonic: &str) -> u64 {
+        self.opcodes.get(mnemonic).copied().unwrap_or(0)
+    }
+
+    /// Every address that was hit, busiest first.
+    pub fn hot_addresses(&self) -> Vec<(usize, Hits)> {
+        let mut addresses: Vec<usize> = self
+            .executions
+            .keys()
+            .chain(self.reads.keys())
+            .chain(self.writes.keys())
+            .copied()
+            .collect();
+        addresses.sort_unstable();
+        addresses.dedup();
+
+        let mut hot: Vec<(usize, Hits)> = addresses
+            .into_iter()
+            .map(|at| (at, self.hits(at)))
+            .collect();
+        hot.sort_by(|(a, a_hits), (b, b_hits)| b_hits.total().cmp(&a_hits.total()).then(a.cmp(b)));
+        hot
+    }
+
+    /// Per-opcode counts followed by per-address hits, both sorted busiest
+    /// first.
+    pub fn table(&self) -> String {
+        let mut text = format!("{} instructions executed\n\n", self.executed);
+
+        let mut opcodes: Vec<(&str, u64)> = self.opcodes.iter().map(|(&op, &n)| (op, n)).collect();
+        opcodes.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        writeln!(text, "{:<6} {:>10} {:>7}", "op", "count", "share").unwrap();
+        for (op, count) in opcodes {
+            let share = 100.0 * count as f64 / self.executed as f64;
+            writeln!(text, "{:<6} {:>10} {:>6.1}%", op, count, share).unwrap();
+        }
+
+        writeln!(
+            text,
+            "\n{:>7} {:>10} {:>10} {:>10}",
+            "address", "executed", "reads", "writes"
+        )
+        .unwrap();
+        for (at, hits) in self.hot_addresses() {
+            writeln!(
+                text,
+                "{:>7} {:>10} {:>10} {:>10}",
+                at, hits.executions, hits.reads, hits.writes
+            )
+            .unwrap();
+        }
+
+        text
+    }
+
+    /// The disassembly of `src` with the hit counts in front of every line.
+    /// Reads and writes are summed over all the words an instruction spans.
+    pub fn annotated(&self, src: &[isize]) -> String {
+        let mut text = format!("{:>10} {:>10} {:>10}\n", "executed", "reads", "writes");
+
+        for line in disassemble(src) {
+            let words = match &line {
+                Line::Instruction(at, op) => *at..at + op.size(),
+                Line::Data(at, _) => *at..at + 1,
+            };
+            let mut hits = Hits {
+                executions: self.hits(line.address()).executions,
+                ..Hits::default()
+            };
+            for at in words {
+                hits.reads += self.hits(at).reads;
+                hits.writes += self.hits(at).writes;
+            }
+
+            writeln!(
+                text,
+                "{:>10} {:>10} {:>10} {}",
+                hits.executions, hits.reads, hits.writes, line
+            )
+            .unwrap();
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::interpreter::{Interpreter, Status};
+    use super::super::DOUBLER;
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn profile(src: &[isize], inputs: &[isize]) -> Profile {
+        let profile = Arc::new(Mutex::new(Profile::default()));
+        let mut program = Interpreter::from_bytecode(src);
+        program.set_tracer(Box::new(Arc::clone(&profile)));
+        for &val in inputs {
+            program.push_input(val);
+        }
+        while program.run().unwrap() != Status::Halted {}
+
+        drop(program);
+        Arc::try_unwrap(profile).unwrap().into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_counts() {
+        let profile = profile(&DOUBLER, &[5, 6, 0]);
+
+        // two rounds of in, jz, mul, out, jnz, then in, jz and hlt
+        assert_eq!(profile.executed(), 2 * 5 + 2 + 1);
+        assert_eq!(profile.opcode_count("in"), 3);
+        assert_eq!(profile.opcode_count("mul"), 2);
+        assert_eq!(profile.opcode_count("hlt"), 1);
+        assert_eq!(
+            profile.hits(15),
+            Hits {
+                executions: 0,
+                reads: 3 + 2 + 2,
+                writes: 3 + 2
+            }
+        );
+        assert_eq!(profile.hot_addresses()[0].0, 15);
+    }
+
+    #[test]
+    fn test_table() {
+        let profile = profile(&[1101, 2, 3, 5, 99, 0], &[]);
+        assert_eq!(
+            profile.table(),
+            concat!(
+                "2 instructions executed\n",
+                "\n",
+                "op          count   share\n",
+                "add             1   50.0%\n",
+                "hlt             1   50.0%\n",
+                "\n",
+                "address   executed      reads     writes\n",
+                "      0          1          0          0\n",
+                "      4          1          0          0\n",
+                "      5          0          0          1\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_annotated() {
+        let profile = profile(&[1001, 5, 3, 5, 99, 7], &[]);
+        assert_eq!(
+            profile.annotated(&[1001, 5, 3, 5, 99, 7]),
+            concat!(
+                "  executed      reads     writes\n",
+                "         1          0          0      0: add [5], #3, [5]\n",
+                "         1          0          0      4: hlt\n",
+                "         0          1          1      5: data 7\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_counts_what_ran() {
+        let profile = Arc::new(Mutex::new(Profile::default()));
+        // jz [5], [6] with [5] holding 1, so [6] is never read
+        let mut program = Interpreter::from_bytecode(&[6, 5, 6, 99, 0, 1, 0]);
+        program.set_tracer(Box::new(Arc::clone(&profile)));
+        for _ in 0..3 {
+            assert_eq!(program.run(), Ok(Status::Halted));
+        }
+
+        let profile = profile.lock().unwrap();
+        assert_eq!(profile.executed(), 2);
+        assert_eq!(profile.opcode_count("hlt"), 1);
+        assert_eq!(profile.hits(3).executions, 1);
+        assert_eq!(profile.hits(5).reads, 1);
+        assert_eq!(profile.hits(6).reads, 0);
+    }
+}
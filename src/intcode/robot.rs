@@ -0,0 +1,455 @@
+use std::collections::HashMap;
+
+use super::error::{ErrorKind, IntcodeError};
+use super::interpreter::{Interpreter, Status};
+
+/// A cell on the grid. `y` grows downwards, the way grids are printed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Point {
+    pub x: isize,
+    pub y: isize,
+}
+
+impl Point {
+    pub fn new(x: isize, y: isize) -> Point {
+        Point { x, y }
+    }
+
+    /// The neighbouring cell in `direction`.
+    pub fn step(self, direction: Direction) -> Point {
+        let (dx, dy) = direction.offset();
+        Point::new(self.x + dx, self.y + dy)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    pub fn offset(self) -> (isize, isize) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::East => (1, 0),
+            Direction::South => (0, 1),
+            Direction::West => (-1, 0),
+        }
+    }
+
+    pub fn left(self) -> Direction {
+        Direction::ALL[(self as usize + 3) % 4]
+    }
+
+    pub fn right(self) -> Direction {
+        Direction::ALL[(self as usize + 1) % 4]
+    }
+
+    pub fn opposite(self) -> Direction {
+        Direction::ALL[(self as usize + 2) % 4]
+    }
+}
+
+/// Where a robot is and which way it faces.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pose {
+    pub position: Point,
+    pub facing: Direction,
+}
+
+impl Pose {
+    pub fn new(position: Point, facing: Direction) -> Pose {
+        Pose { position, facing }
+    }
+
+    pub fn turn_left(&mut self) {
+        self.facing = self.facing.left();
+    }
+
+    pub fn turn_right(&mut self) {
+        self.facing = self.facing.right();
+    }
+
+    pub fn advance(&mut self) {
+        self.position = self.position.step(self.facing);
+    }
+}
+
+/// A grid without edges. Only cells that were set are stored.
+#[derive(Clone, Debug)]
+pub struct Grid<T> {
+    cells: HashMap<Point, T>,
+}
+
+impl<T> Default for Grid<T> {
+    fn default() -> Grid<T> {
+        Grid {
+            cells: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn new() -> Grid<T> {
+        Grid::default()
+    }
+
+    pub fn get(&self, at: Point) -> Option<&T> {
+        self.cells.get(&at)
+    }
+
+    pub fn set(&mut self, at: Point, val: T) {
+        self.cells.insert(at, val);
+    }
+
+    /// Number of cells that were ever set.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Point, &T)> {
+        self.cells.iter()
+    }
+
+    /// Top left and bottom right corners of the smallest rectangle holding
+    /// every cell that was set.
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        let mut points = self.cells.keys();
+        let first = *points.next()?;
+
+        Some(points.fold((first, first), |(min, max), p| {
+            (
+                Point::new(min.x.min(p.x), min.y.min(p.y)),
+                Point::new(max.x.max(p.x), max.y.max(p.y)),
+            )
+        }))
+    }
+
+    /// Draws the area inside `bounds`, one character per cell as picked by
+    /// `draw`, which also sees the cells that were never set.
+    pub fn render(&self, draw: impl Fn(Point, Option<&T>) -> char) -> String {
+        let mut text = String::new();
+        if let Some((min, max)) = self.bounds() {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let at = Point::new(x, y);
+                    text.push(draw(at, self.get(at)));
+                }
+                text.push('\n');
+            }
+        }
+
+        text
+    }
+}
+
+/// Adapts an intcode program to a robot's control loop: every turn the
+/// robot tells the brain what it senses and gets back a fixed number of
+/// values telling it what to do.
+pub struct Brain {
+    interpreter: Interpreter,
+}
+
+impl Brain {
+    pub fn new(interpreter: Interpreter) -> Brain {
+        Brain { interpreter }
+    }
+
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+
+    /// Feeds `sensed` to the program and runs it until it has produced
+    /// `actions` values. Returns `None` if the program halts first.
+    pub fn think(
+        &mut self,
+        sensed: isize,
+        actions: usize,
+    ) -> Result<Option<Vec<isize>>, IntcodeError> {
+        self.interpreter.push_input(sensed);
+
+        let mut output = Vec::with_capacity(actions);
+        while output.len() < actions {
+            match self.interpreter.run()? {
+                Status::Output(val) => output.push(val),
+                Status::Halted => return Ok(None),
+                // the program wants to sense again before acting
+                Status::NeedsInput => {
+                    let ip = self.interpreter.instruction_pointer();
+                    return Err(IntcodeError::new(
+                        ip,
+                        self.interpreter.read(ip),
+                        ErrorKind::InputExhausted,
+                    ));
+                }
+            }
+        }
+
+        Ok(Some(output))
+    }
+}
+
+/// Hull paint colours. Unpainted panels are black.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Color {
+    Black,
+    White,
+}
+
+/// The hull-painting robot: it senses the colour of the panel under it,
+/// then paints the panel and turns left (`0`) or right (`1`) before moving
+/// forward one panel.
+pub struct PaintingRobot {
+    brain: Brain,
+    pose: Pose,
+    hull: Grid<Color>,
+}
+
+impl PaintingRobot {
+    pub fn new(interpreter: Interpreter) -> PaintingRobot {
+        PaintingRobot {
+            brain: Brain::new(interpreter),
+            pose: Pose::new(Point::new(0, 0), Direction::North),
+            hull: Grid::new(),
+        }
+    }
+
+    pub fn pose(&self) -> Pose {
+        self.pose
+    }
+
+    /// Every panel painted so far, however many times.
+    pub fn hull(&self) -> &Grid<Color> {
+        &self.hull
+    }
+
+    /// Paints the panel the robot stands on without the brain's help.
+    pub fn paint(&mut self, color: Color) {
+        self.hull.set(self.pose.position, color);
+    }
+
+    /// Runs until the brain halts.
+    pub fn run(&mut self) -> Result<(), IntcodeError> {
+        loop {
+            let sensed = match self.hull.get(self.pose.position) {
+                Some(Color::White) => 1,
+                _ => 0,
+            };
+
+            let actions = match self.brain.think(sensed, 2)? {
+                Some(actions) => actions,
+                None => return Ok(()),
+            };
+
+            self.paint(if actions[0] == 1 {
+                Color::White
+            } else {
+                Color::Black
+            });
+            if actions[1] == 0 {
+                self.pose.turn_left();
+            } else {
+                self.pose.turn_right();
+            }
+            self.pose.advance();
+        }
+    }
+
+    /// The hull with white panels as `#`.
+    pub fn render(&self) -> String {
+        self.hull.render(|_, color| match color {
+            Some(Color::White) => '#',
+            _ => ' ',
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cell {
+    Wall,
+    Open,
+    Oxygen,
+}
+
+/// The repair droid: it is told to move north (`1`), south (`2`), west
+/// (`3`) or east (`4`) and reports hitting a wall (`0`), moving (`1`) or
+/// moving onto the oxygen system (`2`).
+pub struct RepairDroid {
+    brain: Brain,
+    position: Point,
+    area: Grid<Cell>,
+}
+
+impl RepairDroid {
+    pub fn new(interpreter: Interpreter) -> RepairDroid {
+        let mut area = Grid::new();
+        area.set(Point::new(0, 0), Cell::Open);
+
+        RepairDroid {
+            brain: Brain::new(interpreter),
+            position: Point::new(0, 0),
+            area,
+        }
+    }
+
+    pub fn position(&self) -> Point {
+        self.position
+    }
+
+    /// Everything the droid has bumped into or walked over.
+    pub fn area(&self) -> &Grid<Cell> {
+        &self.area
+    }
+
+    /// Tries to move one cell and records what was there. Returns `None`
+    /// if the brain halted.
+    pub fn try_move(&mut self, direction: Direction) -> Result<Option<Cell>, IntcodeError> {
+        let command = match direction {
+            Direction::North => 1,
+            Direction::South => 2,
+            Direction::West => 3,
+            Direction::East => 4,
+        };
+
+        let status = match self.brain.think(command, 1)? {
+            Some(status) => status[0],
+            None => return Ok(None),
+        };
+
+        let target = self.position.step(direction);
+        let cell = match status {
+            0 => Cell::Wall,
+            1 => Cell::Open,
+            _ => Cell::Oxygen,
+        };
+        self.area.set(target, cell);
+        if cell != Cell::Wall {
+            self.position = target;
+        }
+
+        Ok(Some(cell))
+    }
+
+    /// Walks every reachable cell depth-first and comes back to where it
+    /// started.
+    pub fn explore(&mut self) -> Result<(), IntcodeError> {
+        for &direction in Direction::ALL.iter() {
+            if self.area.get(self.position.step(direction)).is_some() {
+                continue;
+            }
+
+            match self.try_move(direction)? {
+                Some(Cell::Wall) | None => continue,
+                Some(_) => {
+                    self.explore()?;
+                    self.try_move(direction.opposite())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The explored area: `#` walls, `.` open floor, `O` the oxygen system
+    /// and `D` the droid.
+    pub fn render(&self) -> String {
+        self.area.render(|at, cell| match cell {
+            _ if at == self.position => 'D',
+            Some(Cell::Wall) => '#',
+            Some(Cell::Open) => '.',
+            Some(Cell::Oxygen) => 'O',
+            None => ' ',
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::assembler::assemble;
+    use super::*;
+
+    #[test]
+    fn test_pose() {
+        let mut pose = Pose::new(Point::new(0, 0), Direction::North);
+        pose.turn_left();
+        pose.advance();
+        assert_eq!(pose, Pose::new(Point::new(-1, 0), Direction::West));
+
+        pose.turn_right();
+        pose.turn_right();
+        pose.advance();
+        pose.advance();
+        assert_eq!(pose, Pose::new(Point::new(1, 0), Direction::East));
+    }
+
+    #[test]
+    fn test_painting_robot() {
+        // the example moves from the 2019 puzzle, whatever the robot senses
+        let mut src = String::new();
+        for (color, turn) in &[(1, 0), (0, 0), (1, 0), (1, 0), (0, 1), (1, 0), (1, 0)] {
+            src.push_str(&format!("in [s]\nout #{}\nout #{}\n", color, turn));
+        }
+        src.push_str("hlt\ns: data 0\n");
+
+        let mut robot = PaintingRobot::new(Interpreter::from_bytecode(&assemble(&src).unwrap()));
+        robot.run().unwrap();
+
+        assert_eq!(robot.hull().len(), 6);
+        assert_eq!(robot.pose(), Pose::new(Point::new(0, -1), Direction::West));
+        assert_eq!(robot.render(), "  #\n  #\n## \n");
+    }
+
+    #[test]
+    fn test_repair_droid() {
+        // a corridor from x = -1 to x = 2 with the oxygen system at its east end
+        let src = assemble(
+            "
+            loop:   in [cmd]
+                    eq [cmd], #3, [t]
+                    jnz [t], #west
+                    eq [cmd], #4, [t]
+                    jnz [t], #east
+                    out #0
+                    jnz #1, #loop
+            west:   eq [x], #-1, [t]
+                    jnz [t], #wall
+                    add [x], #-1, [x]
+                    jnz #1, #report
+            east:   eq [x], #2, [t]
+                    jnz [t], #wall
+                    add [x], #1, [x]
+            report: eq [x], #2, [t]
+                    add [t], #1, [t]
+                    out [t]
+                    jnz #1, #loop
+            wall:   out #0
+                    jnz #1, #loop
+            cmd:    data 0
+            x:      data 0
+            t:      data 0
+            ",
+        )
+        .unwrap();
+
+        let mut droid = RepairDroid::new(Interpreter::from_bytecode(&src));
+        droid.explore().unwrap();
+
+        assert_eq!(droid.position(), Point::new(0, 0));
+        assert_eq!(droid.area().get(Point::new(2, 0)), Some(&Cell::Oxygen));
+        assert_eq!(droid.render(), " #### \n#.D.O#\n #### \n");
+    }
+}
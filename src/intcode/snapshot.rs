@@ -0,0 +1,210 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+/// Version written into, and expected from, the first line of a snapshot.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// The complete state of an [`Interpreter`](super::Interpreter), enough to
+/// pick a paused program back up in another process.
+///
+/// On disk it is a small text file:
+///
+/// ```text
+/// intcode-snapshot 1
+/// ip 4
+/// rb 0
+/// limit 4194304
+/// inputs 7,8
+/// memory 3,9,8,9,10,9,4,9,99,-1,8
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub instruction_pointer: usize,
+    pub relative_base: isize,
+    pub memory_limit: usize,
+    /// Values pushed with `push_input` that no instruction has read yet.
+    pub inputs: Vec<isize>,
+    pub memory: Vec<isize>,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    UnsupportedVersion(String),
+    /// `line` is 1-based.
+    Malformed {
+        line: usize,
+        message: String,
+    },
+}
+
+impl Snapshot {
+    pub fn write_to(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "intcode-snapshot {}", SNAPSHOT_VERSION)?;
+        writeln!(out, "ip {}", self.instruction_pointer)?;
+        writeln!(out, "rb {}", self.relative_base)?;
+        writeln!(out, "limit {}", self.memory_limit)?;
+        writeln!(out, "inputs {}", join(&self.inputs))?;
+        writeln!(out, "memory {}", join(&self.memory))
+    }
+
+    pub fn read_from(input: &mut dyn BufRead) -> Result<Snapshot, SnapshotError> {
+        let lines: Vec<String> = input.lines().collect::<Result<_, _>>()?;
+
+        match lines.first().map(String::as_str) {
+            Some(header) if header == format!("intcode-snapshot {}", SNAPSHOT_VERSION) => (),
+            Some(header) if header.starts_with("intcode-snapshot ") => {
+                let version = header["intcode-snapshot ".len()..].trim();
+                return Err(SnapshotError::UnsupportedVersion(String::from(version)));
+            }
+            _ => return Err(malformed(1, "not an intcode snapshot")),
+        }
+
+        Ok(Snapshot {
+            instruction_pointer: field(&lines, 2, "ip")?,
+            relative_base: field(&lines, 3, "rb")?,
+            memory_limit: field(&lines, 4, "limit")?,
+            inputs: words(&lines, 5, "inputs")?,
+            memory: words(&lines, 6, "memory")?,
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_to(&mut out)?;
+        out.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Snapshot, SnapshotError> {
+        Snapshot::read_from(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+fn join(words: &[isize]) -> String {
+    let words: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+    words.join(",")
+}
+
+fn malformed(line: usize, message: &str) -> SnapshotError {
+    SnapshotError::Malformed {
+        line,
+        message: String::from(message),
+    }
+}
+
+// the value of the `name value` line at the 1-based `line`
+fn value<'a>(lines: &'a [String], line: usize, name: &str) -> Result<&'a str, SnapshotError> {
+    let text = match lines.get(line - 1) {
+        Some(text) => text,
+        None => return Err(malformed(line, &format!("missing `{}` line", name))),
+    };
+
+    match text.split_once(' ') {
+        Some((key, value)) if key == name => Ok(value.trim()),
+        None if text == name => Ok(""),
+        _ => Err(malformed(line, &format!("expected `{}`", name))),
+    }
+}
+
+fn field<T: std::str::FromStr>(
+    lines: &[String],
+    line: usize,
+    name: &str,
+) -> Result<T, SnapshotError> {
+    value(lines, line, name)?
+        .parse()
+        .map_err(|_| malformed(line, &format!("invalid `{}` value", name)))
+}
+
+fn words(lines: &[String], line: usize, name: &str) -> Result<Vec<isize>, SnapshotError> {
+    let value = value(lines, line, name)?;
+    if value.is_empty() {
+        return Ok(vec![]);
+    }
+
+    value
+        .split(',')
+        .map(|word| word.parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| malformed(line, &format!("invalid `{}` value", name)))
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> SnapshotError {
+        SnapshotError::Io(err)
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "{}", err),
+            SnapshotError::UnsupportedVersion(version) => write!(
+                f,
+                "snapshot version {} is not supported, expected {}",
+                version, SNAPSHOT_VERSION
+            ),
+            SnapshotError::Malformed { line, message } => {
+                write!(f, "line {} of the snapshot: {}", line, message)
+            }
+        }
+    }
+}
+
+impl Error for SnapshotError {}
+
+#[cfg(test)]
+mod tests {
+    use super::super::interpreter::{Interpreter, Status};
+    use super::super::DOUBLER;
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut program = Interpreter::from_bytecode(&DOUBLER);
+        program.push_input(5);
+        assert_eq!(program.run(), Ok(Status::Output(10)));
+        program.push_input(6);
+        program.push_input(0);
+
+        let mut file = vec![];
+        program.snapshot().write_to(&mut file).unwrap();
+        let snapshot = Snapshot::read_from(&mut Cursor::new(file)).unwrap();
+        assert_eq!(snapshot, program.snapshot());
+        assert_eq!(snapshot.inputs, vec![6, 0]);
+
+        let mut restored = Interpreter::from_snapshot(&snapshot);
+        for vm in [&mut program, &mut restored].iter_mut() {
+            assert_eq!(vm.run(), Ok(Status::Output(12)));
+            assert_eq!(vm.run(), Ok(Status::Halted));
+        }
+        assert_eq!(restored.snapshot(), program.snapshot());
+    }
+
+    #[test]
+    fn test_snapshot_errors() {
+        let read = |text: &str| Snapshot::read_from(&mut Cursor::new(String::from(text)));
+
+        match read("intcode-snapshot 2\nip 0\n") {
+            Err(SnapshotError::UnsupportedVersion(version)) => assert_eq!(version, "2"),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        match read("intcode-snapshot 1\nip 0\nrb x\n") {
+            Err(SnapshotError::Malformed { line, message }) => {
+                assert_eq!((line, message.as_str()), (3, "invalid `rb` value"))
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        match read("1,2,3") {
+            Err(SnapshotError::Malformed { line, .. }) => assert_eq!(line, 1),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,728 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use super::error::{ErrorKind, IntcodeError};
+use super::memory::DEFAULT_MEMORY_LIMIT;
+
+// straight-line code never needs anywhere near this many steps, this only
+// stops concrete infinite loops
+const STEP_LIMIT: usize = 1_000_000;
+
+/// A value computed from symbols. Building one through [`Expr::sum`] and
+/// friends folds constants as it goes, which is where a constant overflowing
+/// a word shows up.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Const(isize),
+    Symbol(String),
+    Sum(Box<Expr>, Box<Expr>),
+    Product(Box<Expr>, Box<Expr>),
+    LessThan(Box<Expr>, Box<Expr>),
+    Equals(Box<Expr>, Box<Expr>),
+    /// The word at an address that is only known symbolically, as it was
+    /// when read.
+    Load(Box<Expr>),
+}
+
+impl Expr {
+    pub fn symbol(name: &str) -> Expr {
+        Expr::Symbol(String::from(name))
+    }
+
+    /// `None` if both are constants whose sum overflows.
+    pub fn sum(a: Expr, b: Expr) -> Option<Expr> {
+        match (a, b) {
+            (Expr::Const(a), Expr::Const(b)) => a.checked_add(b).map(Expr::Const),
+            (Expr::Const(0), e) | (e, Expr::Const(0)) => Some(e),
+            (a, b) => Some(Expr::Sum(Box::new(a), Box::new(b))),
+        }
+    }
+
+    /// `None` if both are constants whose product overflows.
+    pub fn product(a: Expr, b: Expr) -> Option<Expr> {
+        match (a, b) {
+            (Expr::Const(a), Expr::Const(b)) => a.checked_mul(b).map(Expr::Const),
+            (Expr::Const(0), _) | (_, Expr::Const(0)) => Some(Expr::Const(0)),
+            (Expr::Const(1), e) | (e, Expr::Const(1)) => Some(e),
+            (a, b) => Some(Expr::Product(Box::new(a), Box::new(b))),
+        }
+    }
+
+    pub fn less_than(a: Expr, b: Expr) -> Expr {
+        match (a, b) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const((a < b) as isize),
+            (a, b) => Expr::LessThan(Box::new(a), Box::new(b)),
+        }
+    }
+
+    pub fn equals(a: Expr, b: Expr) -> Expr {
+        match (a, b) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const((a == b) as isize),
+            (a, b) => Expr::Equals(Box::new(a), Box::new(b)),
+        }
+    }
+
+    /// The expression as a sum of products of symbols. Fails if it
+    /// compares or loads anything, or if a coefficient overflows.
+    pub fn to_polynomial(&self) -> Result<Polynomial, SolveError> {
+        match self {
+            Expr::Const(val) => Ok(Polynomial::constant(*val)),
+            Expr::Symbol(name) => {
+                let mut terms = BTreeMap::new();
+                terms.insert(vec![name.clone()], 1);
+                Ok(Polynomial { terms })
+            }
+            Expr::Sum(a, b) => a
+                .to_polynomial()?
+                .add(&b.to_polynomial()?)
+                .ok_or(SolveError::Overflow),
+            Expr::Product(a, b) => a
+                .to_polynomial()?
+                .mul(&b.to_polynomial()?)
+                .ok_or(SolveError::Overflow),
+            Expr::LessThan(..) | Expr::Equals(..) | Expr::Load(_) => Err(SolveError::NotPolynomial),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Const(val) => write!(f, "{}", val),
+            Expr::Symbol(name) => write!(f, "{}", name),
+            Expr::Sum(a, b) => write!(f, "({} + {})", a, b),
+            Expr::Product(a, b) => write!(f, "({} * {})", a, b),
+            Expr::LessThan(a, b) => write!(f, "({} < {})", a, b),
+            Expr::Equals(a, b) => write!(f, "({} == {})", a, b),
+            Expr::Load(at) => write!(f, "[{}]", at),
+        }
+    }
+}
+
+/// A polynomial over symbols with integer coefficients. Every term is keyed
+/// by the sorted names of the symbols it multiplies, repeated for powers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polynomial {
+    terms: BTreeMap<Vec<String>, isize>,
+}
+
+impl Polynomial {
+    fn constant(val: isize) -> Polynomial {
+        let mut terms = BTreeMap::new();
+        if val != 0 {
+            terms.insert(vec![], val);
+        }
+        Polynomial { terms }
+    }
+
+    // both are `None` if a coefficient overflows
+    fn add(&self, other: &Polynomial) -> Option<Polynomial> {
+        let mut terms = self.terms.clone();
+        for (term, coefficient) in &other.terms {
+            let sum = terms.entry(term.clone()).or_insert(0);
+            *sum = sum.checked_add(*coefficient)?;
+        }
+        terms.retain(|_, coefficient| *coefficient != 0);
+        Some(Polynomial { terms })
+    }
+
+    fn mul(&self, other: &Polynomial) -> Option<Polynomial> {
+        let mut product = Polynomial::constant(0);
+        for (a, a_coefficient) in &self.terms {
+            for (b, b_coefficient) in &other.terms {
+                let mut term: Vec<String> = a.iter().chain(b).cloned().collect();
+                term.sort();
+                let mut terms = BTreeMap::new();
+                terms.insert(term, a_coefficient.checked_mul(*b_coefficient)?);
+                product = product.add(&Polynomial { terms })?;
+            }
+        }
+        Some(product)
+    }
+
+    /// Highest power of `name` in any term.
+    pub fn degree_in(&self, name: &str) -> usize {
+        self.terms
+            .keys()
+            .map(|term| term.iter().filter(|s| *s == name).count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn symbols(&self) -> Vec<&String> {
+        let mut symbols: Vec<&String> = self.terms.keys().flatten().collect();
+        symbols.sort();
+        symbols.dedup();
+        symbols
+    }
+}
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+
+        // highest degree first, so the constant comes last
+        let mut terms: Vec<(&Vec<String>, &isize)> = self.terms.iter().collect();
+        terms.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then(a.cmp(b)));
+        for (i, (term, coefficient)) in terms.into_iter().enumerate() {
+            let sign = if *coefficient < 0 { "-" } else { "+" };
+            match i {
+                0 if *coefficient < 0 => write!(f, "-")?,
+                0 => (),
+                _ => write!(f, " {} ", sign)?,
+            }
+
+            let magnitude = coefficient.unsigned_abs();
+            if term.is_empty() {
+                write!(f, "{}", magnitude)?;
+            } else if magnitude == 1 {
+                write!(f, "{}", term.join("*"))?;
+            } else {
+                write!(f, "{}*{}", magnitude, term.join("*"))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Why symbolic execution couldn't carry on.
+#[derive(Debug, PartialEq)]
+pub enum SymbolicError {
+    /// Whether the jump at `ip` is taken depends on the symbols, so there is
+    /// no single expression for what the program computes.
+    Branch {
+        ip: usize,
+        condition: Expr,
+    },
+    /// The instruction at `ip` writes to, or jumps to, an address that
+    /// depends on the symbols.
+    SymbolicAddress {
+        ip: usize,
+        address: Expr,
+    },
+    /// The word at `ip` is executed but depends on the symbols.
+    SymbolicCode {
+        ip: usize,
+        word: Expr,
+    },
+    Intcode(IntcodeError),
+}
+
+impl fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SymbolicError::Branch { ip, condition } => write!(
+                f,
+                "jump at {} depends on {}, no closed-form solution",
+                ip, condition
+            ),
+            SymbolicError::SymbolicAddress { ip, address } => {
+                write!(f, "instruction {} uses symbolic address {}", ip, address)
+            }
+            SymbolicError::SymbolicCode { ip, word } => {
+                write!(f, "instruction {} is symbolic: {}", ip, word)
+            }
+            SymbolicError::Intcode(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for SymbolicError {}
+
+/// Runs a program with some of its memory cells or inputs standing for
+/// unknown values, so what it computes comes out as an [`Expr`] over them.
+///
+/// Reading through an address that depends on symbols gives an opaque
+/// [`Expr::Load`]; writing or jumping through one, or branching on a
+/// symbolic condition, stops execution with a [`SymbolicError`].
+pub struct SymbolicMachine {
+    memory: Vec<Expr>,
+    instruction_pointer: usize,
+    relative_base: isize,
+    inputs: VecDeque<Expr>,
+    outputs: Vec<Expr>,
+}
+
+impl SymbolicMachine {
+    pub fn new(src: &[isize]) -> SymbolicMachine {
+        SymbolicMachine {
+            memory: src.iter().map(|&word| Expr::Const(word)).collect(),
+            instruction_pointer: 0,
+            relative_base: 0,
+            inputs: VecDeque::new(),
+            outputs: vec![],
+        }
+    }
+
+    /// Replaces the word at `at` with the symbol `name`.
+    pub fn symbolize(&mut self, at: usize, name: &str) {
+        // the cell exists already unless it is past the program
+        if at >= self.memory.len() {
+            self.memory.resize(at + 1, Expr::Const(0));
+        }
+        self.memory[at] = Expr::symbol(name);
+    }
+
+    /// Queues a value, symbolic or not, for the next `Op::Input`.
+    pub fn push_input(&mut self, val: Expr) {
+        self.inputs.push_back(val);
+    }
+
+    pub fn read(&self, at: usize) -> Expr {
+        self.memory.get(at).cloned().unwrap_or(Expr::Const(0))
+    }
+
+    pub fn outputs(&self) -> &[Expr] {
+        &self.outputs
+    }
+
+    /// Runs the program until it halts.
+    pub fn run(&mut self) -> Result<(), SymbolicError> {
+        for _ in 0..STEP_LIMIT {
+            let ip = self.instruction_pointer;
+            let word = match self.read(ip) {
+                Expr::Const(word) => word,
+                word => return Err(SymbolicError::SymbolicCode { ip, word }),
+            };
+            let modes = word / 100;
+
+            match word % 100 {
+                op @ 1 | op @ 2 | op @ 7 | op @ 8 => {
+                    let a = self.arg(1, modes)?;
+                    let b = self.arg(2, modes)?;
+                    let val = match op {
+                        1 => Expr::sum(a, b),
+                        2 => Expr::product(a, b),
+                        7 => Some(Expr::less_than(a, b)),
+                        _ => Some(Expr::equals(a, b)),
+                    }
+                    .ok_or_else(|| self.fault(ErrorKind::ArithmeticOverflow))?;
+                    let at = self.address(3, modes)?;
+                    self.store(at, val)?;
+                    self.instruction_pointer += 4;
+                }
+                3 => {
+                    let val = self
+                        .inputs
+                        .pop_front()
+                        .ok_or_else(|| self.fault(ErrorKind::InputExhausted))?;
+                    let at = self.address(1, modes)?;
+                    self.store(at, val)?;
+                    self.instruction_pointer += 2;
+                }
+                4 => {
+                    let val = self.arg(1, modes)?;
+                    self.outputs.push(val);
+                    self.instruction_pointer += 2;
+                }
+                op @ 5 | op @ 6 => {
+                    let jump = match self.arg(1, modes)? {
+                        Expr::Const(test) => (test != 0) == (op == 5),
+                        condition => return Err(SymbolicError::Branch { ip, condition }),
+                    };
+
+                    self.instruction_pointer = match (jump, self.arg(2, modes)?) {
+                        (false, _) => ip + 3,
+                        (true, Expr::Const(target)) if target >= 0 => target as usize,
+                        (true, Expr::Const(target)) => {
+                            return Err(self.fault(ErrorKind::InvalidAddress(target)))
+                        }
+                        (true, address) => {
+                            return Err(SymbolicError::SymbolicAddress { ip, address })
+                        }
+                    };
+                }
+                9 => {
+                    match self.arg(1, modes)? {
+                        Expr::Const(offset) => {
+                            self.relative_base = self
+                                .relative_base
+                                .checked_add(offset)
+                                .ok_or_else(|| self.fault(ErrorKind::ArithmeticOverflow))?
+                        }
+                        address => return Err(SymbolicError::SymbolicAddress { ip, address }),
+                    }
+                    self.instruction_pointer += 2;
+                }
+                99 => return Ok(()),
+                _ => return Err(self.fault(ErrorKind::UnknownOpcode)),
+            }
+        }
+
+        Err(self.fault(ErrorKind::BudgetExhausted))
+    }
+
+    fn fault(&self, kind: ErrorKind) -> SymbolicError {
+        let ip = self.instruction_pointer;
+        let word = match self.read(ip) {
+            Expr::Const(word) => word,
+            _ => 0,
+        };
+        SymbolicError::Intcode(IntcodeError::new(ip, word, kind))
+    }
+
+    // the address parameter `param` (1-based) refers to, as an expression
+    fn address_expr(&self, param: usize, modes: isize) -> Result<Expr, SymbolicError> {
+        let raw = self.read(self.instruction_pointer + param);
+        let address = match modes / 10isize.pow(param as u32 - 1) % 10 {
+            0 => raw,
+            2 => Expr::sum(Expr::Const(self.relative_base), raw)
+                .ok_or_else(|| self.fault(ErrorKind::ArithmeticOverflow))?,
+            mode => {
+                return Err(self.fault(ErrorKind::InvalidParameterMode {
+                    param,
+                    mode: mode as usize,
+                }))
+            }
+        };
+
+        match address {
+            Expr::Const(at) if at < 0 => Err(self.fault(ErrorKind::InvalidAddress(at))),
+            address => Ok(address),
+        }
+    }
+
+    fn arg(&self, param: usize, modes: isize) -> Result<Expr, SymbolicError> {
+        if modes / 10isize.pow(param as u32 - 1) % 10 == 1 {
+            return Ok(self.read(self.instruction_pointer + param));
+        }
+
+        match self.address_expr(param, modes)? {
+            Expr::Const(at) => Ok(self.read(at as usize)),
+            address => Ok(Expr::Load(Box::new(address))),
+        }
+    }
+
+    fn address(&self, param: usize, modes: isize) -> Result<usize, SymbolicError> {
+        match self.address_expr(param, modes)? {
+            Expr::Const(at) => Ok(at as usize),
+            address => Err(SymbolicError::SymbolicAddress {
+                ip: self.instruction_pointer,
+                address,
+            }),
+        }
+    }
+
+    fn store(&mut self, at: usize, val: Expr) -> Result<(), SymbolicError> {
+        if at >= DEFAULT_MEMORY_LIMIT {
+            return Err(self.fault(ErrorKind::MemoryLimitExceeded {
+                address: at,
+                limit: DEFAULT_MEMORY_LIMIT,
+            }));
+        }
+        if at >= self.memory.len() {
+            self.memory.resize(at + 1, Expr::Const(0));
+        }
+
+        self.memory[at] = val;
+        Ok(())
+    }
+}
+
+/// Why [`solve`] found no values.
+#[derive(Debug, PartialEq)]
+pub enum SolveError {
+    /// The expression compares or loads something, so it isn't a
+    /// polynomial.
+    NotPolynomial,
+    /// Every symbol shows up squared or worse.
+    Nonlinear,
+    /// A symbol in the expression has no domain.
+    UnknownSymbol(String),
+    /// A coefficient of the polynomial doesn't fit in a word.
+    Overflow,
+    NoSolution,
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolveError::NotPolynomial => write!(f, "expression isn't a polynomial"),
+            SolveError::Nonlinear => write!(f, "no symbol appears linearly"),
+            SolveError::UnknownSymbol(name) => write!(f, "no domain given for {}", name),
+            SolveError::Overflow => write!(f, "a coefficient overflows a word"),
+            SolveError::NoSolution => write!(f, "no solution within the domains"),
+        }
+    }
+}
+
+impl Error for SolveError {}
+
+/// Finds values for the symbols in `domains`, in the same order, that make
+/// `expr` equal to `target`.
+///
+/// One symbol that the polynomial is linear in is solved for directly; the
+/// others, if any, are tried across their domains. Symbols that don't
+/// appear in `expr` get the start of their domain. Values that would make
+/// the evaluation overflow are skipped.
+pub fn solve(
+    expr: &Expr,
+    target: isize,
+    domains: &[(&str, RangeInclusive<isize>)],
+) -> Result<Vec<isize>, SolveError> {
+    let polynomial = expr.to_polynomial()?;
+    for symbol in polynomial.symbols() {
+        if !domains.iter().any(|(name, _)| name == symbol) {
+            return Err(SolveError::UnknownSymbol(symbol.clone()));
+        }
+    }
+
+    let mut values: Vec<isize> = domains.iter().map(|(_, domain)| *domain.start()).collect();
+    // the linear symbol with the largest domain saves the most enumeration
+    let unknown = (0..domains.len())
+        .filter(|&i| polynomial.degree_in(domains[i].0) == 1)
+        .max_by_key(|&i| domains[i].1.end().saturating_sub(*domains[i].1.start()));
+    let unknown = match unknown {
+        Some(unknown) => unknown,
+        None if polynomial.symbols().is_empty() => {
+            return if polynomial.terms.get(&vec![]).copied().unwrap_or(0) == target {
+                Ok(values)
+            } else {
+                Err(SolveError::NoSolution)
+            };
+        }
+        None => return Err(SolveError::Nonlinear),
+    };
+
+    // the others that matter, counted through like an odometer
+    let enumerated: Vec<usize> = (0..domains.len())
+        .filter(|&i| i != unknown && polynomial.degree_in(domains[i].0) > 0)
+        .collect();
+
+    loop {
+        let solution = linear_coefficients(&polynomial, domains, unknown, &values)
+            .and_then(|(a, b)| {
+                let rest = target.checked_sub(b)?;
+                match rest.checked_rem(a)? {
+                    0 => rest.checked_div(a),
+                    _ => None,
+                }
+            })
+            .filter(|val| domains[unknown].1.contains(val));
+        if let Some(val) = solution {
+            values[unknown] = val;
+            return Ok(values);
+        }
+
+        let mut carried = true;
+        for &i in &enumerated {
+            if values[i] < *domains[i].1.end() {
+                values[i] += 1;
+                carried = false;
+                break;
+            }
+            values[i] = *domains[i].1.start();
+        }
+        if carried {
+            return Err(SolveError::NoSolution);
+        }
+    }
+}
+
+// with everything but `unknown` fixed to `values` the polynomial is
+// a * unknown + b, or `None` if working out a or b overflows
+fn linear_coefficients(
+    polynomial: &Polynomial,
+    domains: &[(&str, RangeInclusive<isize>)],
+    unknown: usize,
+    values: &[isize],
+) -> Option<(isize, isize)> {
+    let (mut a, mut b) = (0isize, 0isize);
+    for (term, coefficient) in &polynomial.terms {
+        let mut product = *coefficient;
+        for symbol in term.iter().filter(|s| *s != domains[unknown].0) {
+            let i = domains.iter().position(|(name, _)| name == symbol).unwrap();
+            product = product.checked_mul(values[i])?;
+        }
+
+        if term.iter().any(|s| s == domains[unknown].0) {
+            a = a.checked_add(product)?;
+        } else {
+            b = b.checked_add(product)?;
+        }
+    }
+
+    Some((a, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::assembler::assemble;
+    use super::*;
+
+    #[test]
+    fn test_day_2_style_program() {
+        // like day 2, noun and verb are first used as addresses, but what
+        // that reads is overwritten before anything depends on it
+        let src = vec![1, 0, 0, 3, 1002, 1, 100, 3, 1, 3, 2, 0, 1001, 0, 300, 0, 99];
+        let mut machine = SymbolicMachine::new(&src);
+        machine.symbolize(1, "noun");
+        machine.symbolize(2, "verb");
+        machine.run().unwrap();
+
+        let result = machine.read(0);
+        assert_eq!(
+            result.to_polynomial().unwrap().to_string(),
+            "100*noun + verb + 300"
+        );
+        assert_eq!(
+            solve(&result, 4567, &[("noun", 0..=99), ("verb", 0..=99)]),
+            Ok(vec![42, 67])
+        );
+        assert_eq!(
+            solve(&result, 100_000, &[("noun", 0..=99), ("verb", 0..=99)]),
+            Err(SolveError::NoSolution)
+        );
+    }
+
+    #[test]
+    fn test_symbolic_inputs() {
+        // prints in1 * in2 + in1
+        let src = assemble(
+            "
+                    in [a]
+                    in [b]
+                    mul [a], [b], [c]
+                    add [c], [a], [c]
+                    out [c]
+                    hlt
+            a:      data 0
+            b:      data 0
+            c:      data 0
+            ",
+        )
+        .unwrap();
+        let mut machine = SymbolicMachine::new(&src);
+        machine.push_input(Expr::symbol("x"));
+        machine.push_input(Expr::symbol("y"));
+        machine.run().unwrap();
+
+        assert_eq!(machine.outputs().len(), 1);
+        assert_eq!(machine.outputs()[0].to_string(), "((x * y) + x)");
+        // x * (y + 1) = 36 needs trying values of x for y
+        assert_eq!(
+            solve(&machine.outputs()[0], 36, &[("x", 5..=10), ("y", 0..=100)]),
+            Ok(vec![6, 5])
+        );
+    }
+
+    #[test]
+    fn test_branching_is_reported() {
+        let src = assemble(
+            "
+                    in [a]
+                    lt [a], #10, [t]
+                    jnz [t], #small
+                    out #1
+                    hlt
+            small:  out #0
+                    hlt
+            a:      data 0
+            t:      data 0
+            ",
+        )
+        .unwrap();
+        let mut machine = SymbolicMachine::new(&src);
+        machine.push_input(Expr::symbol("x"));
+
+        let err = machine.run().unwrap_err();
+        assert_eq!(
+            err,
+            SymbolicError::Branch {
+                ip: 6,
+                condition: Expr::less_than(Expr::symbol("x"), Expr::Const(10)),
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "jump at 6 depends on (x < 10), no closed-form solution"
+        );
+    }
+
+    #[test]
+    fn test_concrete_branches_are_followed() {
+        // counts [n] down to 0 while adding x each time
+        let src = assemble(
+            "
+                    in [x]
+            loop:   jz [n], #done
+                    add [sum], [x], [sum]
+                    add [n], #-1, [n]
+                    jnz #1, #loop
+            done:   out [sum]
+                    hlt
+            x:      data 0
+            n:      data 3
+            sum:    data 0
+            ",
+        )
+        .unwrap();
+        let mut machine = SymbolicMachine::new(&src);
+        machine.push_input(Expr::symbol("x"));
+        machine.run().unwrap();
+
+        assert_eq!(
+            machine.outputs()[0].to_polynomial().unwrap().to_string(),
+            "3*x"
+        );
+    }
+
+    #[test]
+    fn test_unsolvable() {
+        let x = Expr::symbol("x");
+        assert_eq!(
+            solve(
+                &Expr::product(x.clone(), x.clone()).unwrap(),
+                4,
+                &[("x", 0..=10)]
+            ),
+            Err(SolveError::Nonlinear)
+        );
+        assert_eq!(
+            solve(
+                &Expr::equals(x.clone(), Expr::Const(1)),
+                1,
+                &[("x", 0..=10)]
+            ),
+            Err(SolveError::NotPolynomial)
+        );
+        assert_eq!(
+            solve(&x, 1, &[]),
+            Err(SolveError::UnknownSymbol(String::from("x")))
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert_eq!(Expr::sum(Expr::Const(isize::MAX), Expr::Const(1)), None);
+        let mut machine = SymbolicMachine::new(&[1102, isize::MAX, 2, 0, 99]);
+        assert_eq!(
+            machine.run(),
+            Err(SymbolicError::Intcode(IntcodeError::new(
+                0,
+                1102,
+                ErrorKind::ArithmeticOverflow
+            )))
+        );
+
+        let x = Expr::symbol("x");
+        let huge = Expr::product(Expr::Const(isize::MAX), x.clone()).unwrap();
+        assert_eq!(
+            Expr::product(huge.clone(), Expr::Const(2))
+                .unwrap()
+                .to_polynomial(),
+            Err(SolveError::Overflow)
+        );
+        // x = -1 would need y = 5 + isize::MAX, so it is skipped
+        let expr = Expr::sum(huge, Expr::symbol("y")).unwrap();
+        assert_eq!(
+            solve(&expr, 5, &[("y", 0..=10), ("x", -1..=1)]),
+            Ok(vec![5, 0])
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use super::error::IntcodeError;
+use super::interpreter::Interpreter;
+
+/// Runs `interpreter` to completion on a thread of its own, reading input
+/// from `input` and sending output to `output`. Reading blocks until a value
+/// arrives; if every sender of `input` hangs up first the program fails with
+/// `ErrorKind::InputExhausted`. The interpreter is handed back once it
+/// halts so its memory can be inspected.
+pub fn spawn_with(
+    mut interpreter: Interpreter,
+    mut input: Receiver<isize>,
+    mut output: Sender<isize>,
+) -> JoinHandle<Result<Interpreter, IntcodeError>> {
+    thread::spawn(move || {
+        interpreter.execute_with(&mut input, &mut output)?;
+        Ok(interpreter)
+    })
+}
+
+/// An interpreter running on its own thread, with a channel going in and
+/// one coming out.
+pub struct ThreadedVm {
+    pub input: Sender<isize>,
+    pub output: Receiver<isize>,
+    handle: JoinHandle<Result<Interpreter, IntcodeError>>,
+}
+
+impl ThreadedVm {
+    pub fn spawn(interpreter: Interpreter) -> ThreadedVm {
+        let (input, program_input) = channel();
+        let (program_output, output) = channel();
+
+        ThreadedVm {
+            input,
+            output,
+            handle: spawn_with(interpreter, program_input, program_output),
+        }
+    }
+
+    /// Waits for the program to halt. Panics if the thread running it
+    /// panicked.
+    pub fn join(self) -> Result<Interpreter, IntcodeError> {
+        self.handle.join().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::error::ErrorKind;
+    use super::super::DOUBLER;
+    use super::*;
+
+    fn assert_send<T: Send>() {}
+
+    #[test]
+    fn test_interpreter_is_send() {
+        assert_send::<Interpreter>();
+    }
+
+    #[test]
+    fn test_threaded_vm() {
+        let vm = ThreadedVm::spawn(Interpreter::from_bytecode(&DOUBLER));
+
+        for i in 1..=3 {
+            vm.input.send(i).unwrap();
+            assert_eq!(vm.output.recv(), Ok(i * 2));
+        }
+        vm.input.send(0).unwrap();
+
+        let halted = vm.join().unwrap();
+        assert_eq!(halted.read(15), 0);
+    }
+
+    #[test]
+    fn test_hung_up_input() {
+        let ThreadedVm { input, handle, .. } =
+            ThreadedVm::spawn(Interpreter::from_bytecode(&[3, 0, 99]));
+        drop(input);
+
+        assert_eq!(
+            handle.join().unwrap().err().map(|err| err.kind),
+            Some(ErrorKind::InputExhausted)
+        );
+    }
+
+    #[test]
+    fn test_feedback_loop_on_threads() {
+        // the amplifiers from the 2019 puzzle, each on its own thread
+        let program = vec![
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        let phases = [9, 8, 7, 6, 5];
+
+        let (senders, receivers): (Vec<_>, Vec<_>) = phases.iter().map(|_| channel()).unzip();
+        for (sender, &phase) in senders.iter().zip(phases.iter()) {
+            sender.send(phase).unwrap();
+        }
+        senders[0].send(0).unwrap();
+
+        // every amplifier feeds the next, the last one feeds back through here
+        let (last_output, signals) = channel();
+        let mut outputs = senders[1..].to_vec();
+        outputs.push(last_output);
+        let handles: Vec<_> = receivers
+            .into_iter()
+            .zip(outputs)
+            .map(|(input, output)| spawn_with(Interpreter::from_bytecode(&program), input, output))
+            .collect();
+
+        let mut signal = 0;
+        for val in signals {
+            signal = val;
+            if senders[0].send(val).is_err() {
+                break;
+            }
+        }
+
+        for handle in handles {
+            handle.join().unwrap().unwrap();
+        }
+        assert_eq!(signal, 139629729);
+    }
+}
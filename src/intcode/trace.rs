@@ -0,0 +1,150 @@
+use std::fmt;
+use std::io::prelude::*;
+use std::sync::{Arc, Mutex};
+
+use super::parser::Op;
+
+/// First line of every trace file. Bump the version whenever the line format
+/// changes so traces from different versions aren't diffed by mistake.
+pub const TRACE_HEADER: &str = "#intcode-trace 1";
+
+/// Everything a single executed instruction did.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceStep {
+    pub ip: usize,
+    pub op: Op,
+    /// Values of the parameters the instruction read, in order.
+    pub args: Vec<isize>,
+    /// Addresses of the parameters read from memory, in order. Not part of
+    /// the text format.
+    pub reads: Vec<usize>,
+    /// Address and new value of the memory write, if any.
+    pub write: Option<(usize, isize)>,
+    pub input: Option<isize>,
+    pub output: Option<isize>,
+    /// Target of a jump that was taken.
+    pub jump: Option<usize>,
+    /// New relative base after `Op::AdjustRelativeBase`.
+    pub base: Option<isize>,
+}
+
+/// Receives every instruction an [`Interpreter`](super::Interpreter)
+/// executes once installed with `set_tracer`. Tracers travel with the
+/// interpreter, so they have to be `Send` too.
+pub trait Tracer: Send {
+    fn trace(&mut self, step: &TraceStep);
+}
+
+/// Writes the trace as text, one instruction per line, e.g.
+///
+/// ```text
+/// #intcode-trace 1
+/// 0: in [9] | input 8 | write 9 8
+/// 2: eq [9], [10], [9] | args 8 8 | write 9 1
+/// 6: out [9] | args 1 | output 1
+/// 8: hlt
+/// ```
+pub struct TraceWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> TraceWriter<W> {
+    pub fn new(mut out: W) -> TraceWriter<W> {
+        if writeln!(out, "{}", TRACE_HEADER).is_ok() {
+            //
+        }
+
+        TraceWriter { out }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write + Send> Tracer for TraceWriter<W> {
+    fn trace(&mut self, step: &TraceStep) {
+        if writeln!(self.out, "{}", step).is_ok() {
+            //
+        }
+    }
+}
+
+/// Lets a tracer be looked at while an interpreter owns it: install a clone
+/// of the `Arc` and lock the other one to read what it collected.
+impl<T: Tracer> Tracer for Arc<Mutex<T>> {
+    fn trace(&mut self, step: &TraceStep) {
+        self.lock().unwrap().trace(step);
+    }
+}
+
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.ip, self.op)?;
+
+        if !self.args.is_empty() {
+            let args: Vec<String> = self.args.iter().map(|arg| arg.to_string()).collect();
+            write!(f, " | args {}", args.join(" "))?;
+        }
+        if let Some(val) = self.input {
+            write!(f, " | input {}", val)?;
+        }
+        if let Some((at, val)) = self.write {
+            write!(f, " | write {} {}", at, val)?;
+        }
+        if let Some(val) = self.output {
+            write!(f, " | output {}", val)?;
+        }
+        if let Some(ip) = self.jump {
+            write!(f, " | jump {}", ip)?;
+        }
+        if let Some(base) = self.base {
+            write!(f, " | base {}", base)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::interpreter::Interpreter;
+    use super::super::io::IterInput;
+    use super::*;
+    use std::io;
+
+    // lets the test read what the boxed tracer wrote
+    #[derive(Clone)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_trace_writer() {
+        let buffer = SharedBuffer(Arc::new(Mutex::new(vec![])));
+        let mut program = Interpreter::from_bytecode(&[3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8]);
+        program.set_tracer(Box::new(TraceWriter::new(buffer.clone())));
+        program
+            .execute_with(&mut IterInput(Some(8).into_iter()), &mut vec![])
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap(),
+            concat!(
+                "#intcode-trace 1\n",
+                "0: in [9] | input 8 | write 9 8\n",
+                "2: eq [9], [10], [9] | args 8 8 | write 9 1\n",
+                "6: out [9] | args 1 | output 1\n",
+                "8: hlt\n",
+            )
+        );
+    }
+}
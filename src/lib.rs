@@ -7,4 +7,4 @@ pub mod day3;
 pub mod day4;
 pub mod day5;
 
-mod intcode;
+pub mod intcode;
@@ -5,20 +5,36 @@ use aocrs::day2;
 use aocrs::day3;
 use aocrs::day4;
 use aocrs::day5;
+use aocrs::intcode;
 
 use std::env;
 use std::fs;
+use std::io;
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 
 fn main() {
     let config = parse_args();
 
-    match config.day {
-        1 => run_day_1(&config),
-        2 => run_day_2(&config),
-        3 => run_day_3(&config),
-        4 => run_day_4(&config),
-        5 => run_day_5(&config),
-        _ => panic!("Unrecognized day"),
+    match config.command.as_str() {
+        "1" => run_day_1(&config),
+        "2" => run_day_2(&config),
+        "3" => run_day_3(&config),
+        "4" => run_day_4(&config),
+        "5" => run_day_5(&config),
+        "disasm" => run_disassembler(&config),
+        "cfg" => run_cfg(&config),
+        "debug" => run_debugger(&config),
+        "trace" => run_traced(&config),
+        "profile" => run_profiled(&config),
+        "ascii" => run_ascii(&config),
+        "play" => run_adventure(&config),
+        "arcade" => run_arcade(&config),
+        "fuzz" => run_fuzzer(&config),
+        "convert" => run_converter(&config),
+        _ => panic!("Unrecognized command"),
     }
 }
 
@@ -31,31 +47,27 @@ fn read_input_data(filename: &str) -> Vec<u32> {
 }
 
 fn read_file(filename: &str) -> Vec<String> {
-    fs::read_to_string(&filename)
+    fs::read_to_string(filename)
         .unwrap()
         .split('\n')
-        .map(|val| String::from(val))
+        .map(String::from)
         .collect()
 }
 
+// programs can be comma-separated text or binary images, whose metadata
+// says where to start running them
+fn read_intcode_image(filename: &str) -> intcode::Image {
+    match intcode::parse_program(&fs::read(filename).unwrap()) {
+        Ok(image) => image,
+        Err(err) => {
+            eprintln!("{}: {}", filename, err);
+            process::exit(1);
+        }
+    }
+}
+
 fn read_intcode_src(filename: &str) -> Vec<isize> {
-    fs::read_to_string(filename)
-        .unwrap()
-        // ignore whitespace
-        .split_whitespace()
-        .flat_map(|line| {
-            line.split(',').flat_map(|val| {
-                let word = val.parse::<isize>();
-                match word {
-                    Ok(val) => vec![val],
-                    Err(_) => {
-                        println!("[{}] did not product a good value", val);
-                        vec![]
-                    }
-                }
-            })
-        })
-        .collect()
+    read_intcode_image(filename).words
 }
 
 fn run_day_2(config: &Config) {
@@ -75,7 +87,7 @@ fn run_day_3(config: &Config) {
     let wire_a = &input[0];
     let wire_b = &input[1];
 
-    let distance = day3::run(&wire_a, &wire_b);
+    let distance = day3::run(wire_a, wire_b);
     match distance {
         Some(d) => println!("intersection point distance: {}", d),
         None => println!("Wires do not intersect"),
@@ -87,22 +99,251 @@ fn run_day_4(_: &Config) {
 }
 
 fn run_day_5(_: &Config) {
-    day5::part1();
+    if let Err(err) = day5::part1() {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn run_disassembler(config: &Config) {
+    let src = read_intcode_src(config.input_filename.as_ref().unwrap());
+    print!("{}", intcode::listing(&src));
+}
+
+fn run_cfg(config: &Config) {
+    let src = read_intcode_src(config.input_filename.as_ref().unwrap());
+    let cfg = intcode::control_flow_graph(&src);
+
+    print!("{}", cfg.summary());
+    if let Some(dot_file) = config.output_filename.as_ref() {
+        fs::write(dot_file, cfg.to_dot()).unwrap();
+    }
+}
+
+fn run_debugger(config: &Config) {
+    let image = read_intcode_image(config.input_filename.as_ref().unwrap());
+    let mut debugger = intcode::Debugger::new(image.interpreter());
+    debugger
+        .repl(&mut io::stdin().lock(), &mut io::stdout())
+        .unwrap();
+}
+
+fn run_traced(config: &Config) {
+    let image = read_intcode_image(config.input_filename.as_ref().unwrap());
+    let trace_file = fs::File::create(config.output_filename.as_ref().unwrap()).unwrap();
+
+    let mut interpreter = image.interpreter();
+    interpreter.set_tracer(Box::new(intcode::TraceWriter::new(io::BufWriter::new(
+        trace_file,
+    ))));
+    let result = interpreter.execute();
+
+    // dropping the tracer flushes the trace file
+    interpreter.take_tracer();
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn run_profiled(config: &Config) {
+    let image = read_intcode_image(config.input_filename.as_ref().unwrap());
+    let profile = Arc::new(Mutex::new(intcode::Profile::default()));
+
+    let mut interpreter = image.interpreter();
+    interpreter.set_tracer(Box::new(Arc::clone(&profile)));
+    let result = interpreter.execute();
+
+    let profile = profile.lock().unwrap();
+    let report = format!("{}\n{}", profile.table(), profile.annotated(&image.words));
+    match config.output_filename.as_ref() {
+        Some(report_file) => fs::write(report_file, report).unwrap(),
+        // stdout belongs to the program
+        None => eprint!("{}", report),
+    }
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn run_ascii(config: &Config) {
+    let image = read_intcode_image(config.input_filename.as_ref().unwrap());
+    let result = image.interpreter().execute_with(
+        &mut intcode::AsciiStdinInput::default(),
+        &mut intcode::AsciiStdoutOutput,
+    );
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn run_adventure(config: &Config) {
+    let image = read_intcode_image(config.input_filename.as_ref().unwrap());
+    let interpreter = match config.snapshot_filename.as_ref() {
+        // resume a game saved with `!write`
+        Some(path) => match intcode::Snapshot::load(path) {
+            Ok(snapshot) => intcode::Interpreter::from_snapshot(&snapshot),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        },
+        None => image.interpreter(),
+    };
+
+    intcode::play(
+        intcode::AsciiComputer::new(interpreter),
+        &mut io::stdin().lock(),
+        &mut io::stdout(),
+    )
+    .unwrap();
+}
+
+fn run_arcade(config: &Config) {
+    let image = read_intcode_image(config.input_filename.as_ref().unwrap());
+    let mut arcade = intcode::Arcade::new(image.interpreter());
+    arcade.insert_quarters();
+
+    let mut frame = 0;
+    let result = match config.output_filename.as_ref() {
+        // one PPM image per frame in the given directory
+        Some(dir) => arcade.run(&mut intcode::AutoPlayer, &mut |screen| {
+            let path = format!("{}/frame_{:05}.ppm", dir, frame);
+            let mut file = io::BufWriter::new(fs::File::create(path).unwrap());
+            screen.write_ppm(&mut file, 8).unwrap();
+            frame += 1;
+        }),
+        // clears the terminal before every frame
+        None => arcade.run(&mut intcode::AutoPlayer, &mut |screen| {
+            print!("\x1b[2J\x1b[H{}", screen.render())
+        }),
+    };
+
+    match result {
+        Ok(score) => println!("final score: {}", score),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+}
+
+fn run_fuzzer(config: &Config) {
+    let cases = config.fuzz_cases.unwrap_or(10_000);
+    let seed = config.fuzz_seed.unwrap_or(1);
+
+    let mut rng = intcode::XorShift::new(seed);
+    match intcode::fuzz(
+        &mut rng,
+        cases,
+        &mut intcode::interpreter,
+        &mut intcode::fast_interpreter,
+    ) {
+        None => println!("{} cases, no differences", cases),
+        Some(mismatch) => {
+            eprintln!("Interpreter and FastInterpreter disagree:\n{}", mismatch);
+            process::exit(1);
+        }
+    }
+}
+
+// text becomes a binary image named after the file, and an image becomes
+// text again
+fn run_converter(config: &Config) {
+    let input = config.input_filename.as_ref().unwrap();
+    let output = config.output_filename.as_ref().unwrap();
+
+    let is_image = intcode::is_image(&fs::read(input).unwrap());
+    let image = read_intcode_image(input);
+    let result = if is_image {
+        // text has no room for metadata, and without the entry point the
+        // program would start running from 0
+        if let Some(meta) = image.metadata.as_ref() {
+            eprintln!(
+                "warning: {} loses its metadata: name {:?}, entry point {}, {} symbols",
+                output,
+                meta.name,
+                meta.entry_point,
+                meta.symbols.len()
+            );
+        }
+        fs::write(
+            output,
+            format!("{}\n", intcode::to_bytecode_string(&image.words)),
+        )
+    } else {
+        let name = Path::new(input).file_stem().unwrap().to_string_lossy();
+        intcode::Image {
+            metadata: Some(intcode::Metadata {
+                name: name.into_owned(),
+                ..intcode::Metadata::default()
+            }),
+            ..image
+        }
+        .save(output)
+    };
+
+    if let Err(err) = result {
+        eprintln!("{}: {}", output, err);
+        process::exit(1);
+    }
 }
 
 fn parse_args() -> Config {
     let options: Vec<String> = env::args().collect();
-    Config {
-        day: options[1].parse().unwrap(),
+    let mut config = Config {
+        command: String::clone(&options[1]),
         input_filename: if options.len() >= 3 {
             Some(String::clone(&options[2]))
         } else {
             None
         },
+        output_filename: options.get(3).cloned(),
+        snapshot_filename: None,
+        fuzz_cases: None,
+        fuzz_seed: None,
+    };
+
+    // `play <program> [snapshot]` resumes from a snapshot, it writes nothing
+    if config.command == "play" {
+        config.snapshot_filename = config.output_filename.take();
+    }
+
+    // `fuzz [cases] [seed]` takes numbers rather than files
+    if config.command == "fuzz" {
+        config.fuzz_cases = config
+            .input_filename
+            .take()
+            .map(|arg| parse_fuzz_number(&arg));
+        config.fuzz_seed = config
+            .output_filename
+            .take()
+            .map(|arg| parse_fuzz_number(&arg));
+    }
+
+    config
+}
+
+fn parse_fuzz_number<T: FromStr>(arg: &str) -> T {
+    match arg.parse() {
+        Ok(val) => val,
+        Err(_) => {
+            eprintln!("not a number: {}", arg);
+            eprintln!("usage: aocrs fuzz [cases] [seed]");
+            process::exit(1);
+        }
     }
 }
 
 struct Config {
     input_filename: Option<String>,
-    day: u32,
+    output_filename: Option<String>,
+    snapshot_filename: Option<String>,
+    fuzz_cases: Option<usize>,
+    fuzz_seed: Option<u64>,
+    command: String,
 }
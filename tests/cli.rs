@@ -0,0 +1,70 @@
+//! Runs the `aocrs` binary on programs written to a temporary directory.
+
+use aocrs::intcode::{Image, Metadata};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{self, Command, Output};
+
+// a file name no other test run will use at the same time
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("aocrs-{}-{}", process::id(), name))
+}
+
+fn aocrs(args: &[&PathBuf], command: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_aocrs"))
+        .arg(command)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+// prints "B" from 0 but "A\n" from the entry point at 3
+fn entry_point_image() -> Image {
+    Image {
+        words: vec![104, 66, 99, 104, 65, 104, 10, 99],
+        metadata: Some(Metadata {
+            name: String::from("entry"),
+            entry_point: 3,
+            symbols: vec![(String::from("start"), 3)],
+        }),
+    }
+}
+
+#[test]
+fn runs_an_image_from_its_entry_point() {
+    let image_path = temp_path("entry.icb");
+    entry_point_image().save(&image_path).unwrap();
+
+    let output = aocrs(&[&image_path], "ascii");
+    fs::remove_file(&image_path).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "A\n");
+}
+
+#[test]
+fn converting_to_text_warns_about_lost_metadata() {
+    let image_path = temp_path("lossy.icb");
+    let text_path = temp_path("lossy.txt");
+    entry_point_image().save(&image_path).unwrap();
+
+    let output = aocrs(&[&image_path, &text_path], "convert");
+    let text = fs::read_to_string(&text_path).unwrap();
+    fs::remove_file(&image_path).unwrap();
+    fs::remove_file(&text_path).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(text, "104,66,99,104,65,104,10,99\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("entry point 3"));
+}
+
+#[test]
+fn fuzz_rejects_a_case_count_that_isnt_a_number() {
+    let output = aocrs(&[&PathBuf::from("many")], "fuzz");
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("not a number: many"));
+    assert!(stderr.contains("usage: aocrs fuzz [cases] [seed]"));
+}
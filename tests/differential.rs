@@ -0,0 +1,215 @@
+//! Fuzzes both intcode engines against a reference interpreter written as
+//! plainly as possible, straight from the puzzle descriptions.
+
+use aocrs::intcode::{
+    fast_interpreter, fuzz, interpreter, Case, Ending, ErrorKind, IntcodeError, Outcome, XorShift,
+    FUZZ_MEMORY_LIMIT,
+};
+
+// the library's test fixture of the same name, which integration tests
+// can't see
+const DOUBLER: [isize; 16] = [
+    3, 15, 1006, 15, 14, 1002, 15, 2, 15, 4, 15, 1105, 1, 0, 99, 0,
+];
+
+struct Reference {
+    memory: Vec<isize>,
+    limit: usize,
+    ip: usize,
+    base: isize,
+}
+
+impl Reference {
+    fn word(&self, at: usize) -> isize {
+        self.memory.get(at).copied().unwrap_or(0)
+    }
+
+    fn fail(&self, kind: ErrorKind) -> IntcodeError {
+        IntcodeError::new(self.ip, self.word(self.ip), kind)
+    }
+
+    fn mode(&self, param: usize) -> usize {
+        (self.word(self.ip) / 100) as usize / 10usize.pow(param as u32 - 1) % 10
+    }
+
+    fn address(&self, param: usize) -> Result<usize, IntcodeError> {
+        let raw = self.word(self.ip + param);
+        let at = match self.mode(param) {
+            0 => raw,
+            _ => self
+                .base
+                .checked_add(raw)
+                .ok_or_else(|| self.fail(ErrorKind::ArithmeticOverflow))?,
+        };
+
+        if at < 0 {
+            return Err(self.fail(ErrorKind::InvalidAddress(at)));
+        }
+        Ok(at as usize)
+    }
+
+    fn arg(&self, param: usize) -> Result<isize, IntcodeError> {
+        match self.mode(param) {
+            1 => Ok(self.word(self.ip + param)),
+            _ => Ok(self.word(self.address(param)?)),
+        }
+    }
+
+    fn store(&mut self, param: usize, val: isize) -> Result<(), IntcodeError> {
+        let at = self.address(param)?;
+        if at >= self.limit {
+            return Err(self.fail(ErrorKind::MemoryLimitExceeded {
+                address: at,
+                limit: self.limit,
+            }));
+        }
+
+        if at >= self.memory.len() {
+            self.memory.resize(at + 1, 0);
+        }
+        self.memory[at] = val;
+        Ok(())
+    }
+
+    // the whole instruction is decoded before anything runs, so a bad
+    // parameter fails even if execution wouldn't get to it
+    fn decode(&self) -> Result<isize, IntcodeError> {
+        let op = self.word(self.ip) % 100;
+        let (params, written) = match op {
+            1 | 2 | 7 | 8 => (3, Some(3)),
+            3 => (1, Some(1)),
+            4 | 9 => (1, None),
+            5 | 6 => (2, None),
+            99 => (0, None),
+            _ => return Err(self.fail(ErrorKind::UnknownOpcode)),
+        };
+
+        for param in 1..=params {
+            let mode = self.mode(param);
+            if mode > 2 || mode == 1 && written == Some(param) {
+                return Err(self.fail(ErrorKind::InvalidParameterMode { param, mode }));
+            }
+
+            let raw = self.word(self.ip + param);
+            if mode == 0 && raw < 0 {
+                return Err(self.fail(ErrorKind::InvalidAddress(raw)));
+            }
+        }
+
+        Ok(op)
+    }
+
+    fn run(&mut self, case: &Case, outputs: &mut Vec<isize>) -> Result<(), IntcodeError> {
+        let mut inputs = case.inputs.iter();
+        let mut budget = case.budget;
+
+        loop {
+            let op = self.decode()?;
+            if op == 99 {
+                return Ok(());
+            }
+            let waits = op == 3 && inputs.as_slice().is_empty();
+            if budget == 0 && !waits {
+                return Err(self.fail(ErrorKind::BudgetExhausted));
+            }
+
+            match op {
+                1 => {
+                    let val = self
+                        .arg(1)?
+                        .checked_add(self.arg(2)?)
+                        .ok_or_else(|| self.fail(ErrorKind::ArithmeticOverflow))?;
+                    self.store(3, val)?;
+                    self.ip += 4;
+                }
+                2 => {
+                    let val = self
+                        .arg(1)?
+                        .checked_mul(self.arg(2)?)
+                        .ok_or_else(|| self.fail(ErrorKind::ArithmeticOverflow))?;
+                    self.store(3, val)?;
+                    self.ip += 4;
+                }
+                3 => {
+                    let val = *inputs
+                        .next()
+                        .ok_or_else(|| self.fail(ErrorKind::InputExhausted))?;
+                    self.store(1, val)?;
+                    self.ip += 2;
+                }
+                4 => {
+                    outputs.push(self.arg(1)?);
+                    self.ip += 2;
+                }
+                5 | 6 => {
+                    if (self.arg(1)? != 0) == (op == 5) {
+                        let target = self.arg(2)?;
+                        if target < 0 {
+                            return Err(self.fail(ErrorKind::InvalidAddress(target)));
+                        }
+                        self.ip = target as usize;
+                    } else {
+                        self.ip += 3;
+                    }
+                }
+                7 => {
+                    let val = (self.arg(1)? < self.arg(2)?) as isize;
+                    self.store(3, val)?;
+                    self.ip += 4;
+                }
+                8 => {
+                    let val = (self.arg(1)? == self.arg(2)?) as isize;
+                    self.store(3, val)?;
+                    self.ip += 4;
+                }
+                _ => {
+                    self.base = self
+                        .base
+                        .checked_add(self.arg(1)?)
+                        .ok_or_else(|| self.fail(ErrorKind::ArithmeticOverflow))?;
+                    self.ip += 2;
+                }
+            }
+            budget -= 1;
+        }
+    }
+}
+
+fn reference(case: &Case) -> Outcome {
+    let mut machine = Reference {
+        memory: case.program.clone(),
+        limit: FUZZ_MEMORY_LIMIT.max(case.program.len()),
+        ip: 0,
+        base: 0,
+    };
+    let mut outputs = vec![];
+    let result = machine.run(case, &mut outputs);
+    Outcome::new(machine.memory, outputs, result)
+}
+
+#[test]
+fn reference_runs_the_doubler() {
+    let outcome = reference(&Case {
+        program: DOUBLER.to_vec(),
+        inputs: vec![5, 6, 0],
+        budget: 100,
+    });
+    assert_eq!(outcome.outputs, vec![10, 12]);
+    assert_eq!(outcome.ending, Ending::Halted);
+}
+
+#[test]
+fn interpreter_matches_reference() {
+    let mut rng = XorShift::new(25);
+    if let Some(mismatch) = fuzz(&mut rng, 5000, &mut reference, &mut interpreter) {
+        panic!("reference and Interpreter disagree:\n{}", mismatch);
+    }
+}
+
+#[test]
+fn fast_interpreter_matches_reference() {
+    let mut rng = XorShift::new(1225);
+    if let Some(mismatch) = fuzz(&mut rng, 5000, &mut reference, &mut fast_interpreter) {
+        panic!("reference and FastInterpreter disagree:\n{}", mismatch);
+    }
+}